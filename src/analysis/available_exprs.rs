@@ -0,0 +1,176 @@
+use std::collections::HashSet;
+
+use koopa::ir::{BasicBlock, BinaryOp, FunctionData, Value, ValueKind};
+
+use super::cfg::Cfg;
+use super::dataflow::{solve, Analysis, Direction, Solution};
+use super::mem2reg::is_promotable;
+
+/// what an instruction computes, values are SSA so only loads are killed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Expr {
+    Binary(BinaryOp, Operand, Operand),
+    Load(Value),
+}
+
+/// constants are compared by what they are, each one is its own value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operand {
+    Value(Value),
+    Const(i32),
+}
+
+/// expressions computed on every path to a point and not killed since,
+/// with the instructions which computed them
+pub struct AvailableExprs {
+    solution: Solution<HashSet<Expr>>,
+}
+
+impl AvailableExprs {
+    pub fn new(func: &FunctionData, cfg: &Cfg) -> Self {
+        let mut all = HashSet::new();
+        let mut slots = HashSet::new();
+        for node in func.layout().bbs().nodes() {
+            for &inst in node.insts().keys() {
+                all.extend(expr(func, inst));
+                if is_promotable(func, inst) {
+                    slots.insert(inst);
+                }
+            }
+        }
+        let solution = solve(&Available { all, slots }, func, cfg);
+        AvailableExprs { solution }
+    }
+
+    pub fn available_in(&self, bb: BasicBlock) -> &HashSet<Expr> {
+        self.solution.entry(bb)
+    }
+
+    pub fn available_before(&self, inst: Value) -> &HashSet<Expr> {
+        self.solution.before(inst)
+    }
+
+    /// whether `inst` computes again what every path to it has computed
+    pub fn is_redundant(&self, func: &FunctionData, inst: Value) -> bool {
+        expr(func, inst).is_some_and(|e| self.available_before(inst).contains(&e))
+    }
+}
+
+/// the expression `inst` computes, if it's one that is tracked
+pub fn expr(func: &FunctionData, inst: Value) -> Option<Expr> {
+    let operand = |value: Value| {
+        if value.is_global() {
+            return Operand::Value(value);
+        }
+        match func.dfg().value(value).kind() {
+            ValueKind::Integer(int) => Operand::Const(int.value()),
+            _ => Operand::Value(value),
+        }
+    };
+    match func.dfg().value(inst).kind() {
+        ValueKind::Binary(binary) => {
+            Some(Expr::Binary(binary.op(), operand(binary.lhs()), operand(binary.rhs())))
+        }
+        ValueKind::Load(load) => Some(Expr::Load(load.src())),
+        _ => None,
+    }
+}
+
+/// a store kills the loads of its slot, or when it goes through a pointer,
+/// the loads of anything but slots, a call kills those too
+struct Available {
+    all: HashSet<Expr>,
+    // allocs whose address never escapes
+    slots: HashSet<Value>,
+}
+
+impl Available {
+    fn kill_memory(&self, fact: &mut HashSet<Expr>) {
+        fact.retain(|e| !matches!(e, Expr::Load(src) if !self.slots.contains(src)));
+    }
+}
+
+impl Analysis for Available {
+    type Fact = HashSet<Expr>;
+
+    const DIRECTION: Direction = Direction::Forward;
+
+    fn boundary(&self, _func: &FunctionData) -> Self::Fact {
+        HashSet::new()
+    }
+
+    fn top(&self, _func: &FunctionData) -> Self::Fact {
+        self.all.clone()
+    }
+
+    fn meet(&self, fact: &mut Self::Fact, other: &Self::Fact) {
+        fact.retain(|e| other.contains(e));
+    }
+
+    fn transfer(&self, func: &FunctionData, inst: Value, fact: &mut Self::Fact) {
+        match func.dfg().value(inst).kind() {
+            ValueKind::Store(store) if self.slots.contains(&store.dest()) => {
+                fact.remove(&Expr::Load(store.dest()));
+            }
+            ValueKind::Store(_) | ValueKind::Call(_) => self.kill_memory(fact),
+            _ => fact.extend(expr(func, inst)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::AvailableExprs;
+    use crate::analysis::cfg::Cfg;
+    use crate::analysis::test_util::{bb, only_func, parse, value};
+
+    #[test]
+    fn redundant() {
+        let program = parse(
+            r"
+decl @h()
+
+fun @f(@p: *i32, @n: i32): i32 {
+%entry:
+  @x = alloc i32
+  store 1, @x
+  %a0 = add @n, 1
+  %l0 = load @x
+  %q0 = load @p
+  br @n, %then, %else
+%then:
+  %b0 = mul @n, 2
+  call @h()
+  jump %end
+%else:
+  %b1 = mul @n, 2
+  %c0 = sub @n, 1
+  jump %end
+%end:
+  %a1 = add @n, 1
+  %b2 = mul @n, 2
+  %c1 = sub @n, 1
+  %l1 = load @x
+  %q1 = load @p
+  store 5, @x
+  %l2 = load @x
+  ret %a1
+}",
+        );
+        let func = only_func(&program);
+        let avail = AvailableExprs::new(func, &Cfg::new(func));
+        let redundant = |name| avail.is_redundant(func, value(func, name));
+
+        assert!(avail.available_in(bb(func, "entry")).is_empty());
+        assert!(!redundant("%a0"));
+        assert!(redundant("%a1"));
+        // computed on both paths
+        assert!(redundant("%b2"));
+        // computed on one path only
+        assert!(!redundant("%c1"));
+        // a call can't write to a slot, but it can to anything else
+        assert!(redundant("%l1"));
+        assert!(!redundant("%q1"));
+        assert!(!redundant("%l2"));
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::{HashMap, HashSet};
+
+use koopa::ir::{BasicBlock, FunctionData, ValueKind};
+
+/// control flow graph of a function with a body, edges come from `br` and `jump`
+pub struct Cfg {
+    entry: BasicBlock,
+    preds: HashMap<BasicBlock, Vec<BasicBlock>>,
+    succs: HashMap<BasicBlock, Vec<BasicBlock>>,
+    // blocks reachable from the entry, in reverse postorder
+    rpo: Vec<BasicBlock>,
+    rpo_index: HashMap<BasicBlock, usize>,
+}
+
+impl Cfg {
+    pub fn new(func: &FunctionData) -> Self {
+        let entry = func.layout().entry_bb().expect("function without a body");
+        let mut preds: HashMap<_, Vec<_>> = HashMap::new();
+        let mut succs = HashMap::new();
+        for &bb in func.layout().bbs().keys() {
+            let bb_succs = successors(func, bb);
+            for &succ in &bb_succs {
+                preds.entry(succ).or_default().push(bb);
+            }
+            succs.insert(bb, bb_succs);
+        }
+
+        let mut postorder = vec![];
+        let mut visited = HashSet::from([entry]);
+        // blocks with the index of the next successor to visit
+        let mut stack = vec![(entry, 0)];
+        while let Some((bb, i)) = stack.pop() {
+            match succs[&bb].get(i) {
+                Some(&succ) => {
+                    stack.push((bb, i + 1));
+                    if visited.insert(succ) {
+                        stack.push((succ, 0));
+                    }
+                }
+                None => postorder.push(bb),
+            }
+        }
+        postorder.reverse();
+        let rpo_index = postorder.iter().enumerate().map(|(i, &bb)| (bb, i)).collect();
+
+        Cfg {
+            entry,
+            preds,
+            succs,
+            rpo: postorder,
+            rpo_index,
+        }
+    }
+
+    pub fn entry(&self) -> BasicBlock {
+        self.entry
+    }
+
+    /// predecessors of `bb`, including unreachable ones
+    pub fn preds(&self, bb: BasicBlock) -> &[BasicBlock] {
+        self.preds.get(&bb).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn succs(&self, bb: BasicBlock) -> &[BasicBlock] {
+        self.succs.get(&bb).map_or(&[], Vec::as_slice)
+    }
+
+    /// the reachable blocks, each one after all of its predecessors except
+    /// along back edges
+    pub fn rpo(&self) -> &[BasicBlock] {
+        &self.rpo
+    }
+
+    /// position of `bb` in `rpo`, `None` if it's unreachable
+    pub fn rpo_index(&self, bb: BasicBlock) -> Option<usize> {
+        self.rpo_index.get(&bb).copied()
+    }
+
+    pub fn is_reachable(&self, bb: BasicBlock) -> bool {
+        self.rpo_index.contains_key(&bb)
+    }
+}
+
+/// the blocks the terminator of `bb` may go to, once each
+pub fn successors(func: &FunctionData, bb: BasicBlock) -> Vec<BasicBlock> {
+    let node = func.layout().bbs().node(&bb).unwrap();
+    let term = match node.insts().back_key() {
+        Some(&term) => term,
+        None => return vec![],
+    };
+    match func.dfg().value(term).kind() {
+        ValueKind::Jump(jump) => vec![jump.target()],
+        ValueKind::Branch(br) if br.true_bb() == br.false_bb() => vec![br.true_bb()],
+        ValueKind::Branch(br) => vec![br.true_bb(), br.false_bb()],
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Cfg;
+    use crate::analysis::test_util::{bb, only_func, parse, DIAMOND_IN_LOOP};
+
+    #[test]
+    fn diamond_in_loop() {
+        let program = parse(DIAMOND_IN_LOOP);
+        let func = only_func(&program);
+        let cfg = Cfg::new(func);
+        let [entry, cond, then, left, right, join, end] =
+            ["entry", "cond", "then", "left", "right", "join", "end"].map(|name| bb(func, name));
+
+        assert_eq!(cfg.entry(), entry);
+        assert_eq!(cfg.succs(cond), [then, end]);
+        assert_eq!(cfg.succs(end), []);
+        let mut preds = cfg.preds(cond).to_vec();
+        preds.sort_by_key(|&p| cfg.rpo_index(p));
+        assert_eq!(preds, [entry, join]);
+        assert_eq!(cfg.preds(join).len(), 2);
+
+        assert_eq!(cfg.rpo().len(), 7);
+        assert_eq!(cfg.rpo()[0], entry);
+        let index = |bb| cfg.rpo_index(bb).unwrap();
+        assert!(index(cond) < index(then) && index(cond) < index(end));
+        assert!(index(left) < index(join) && index(right) < index(join));
+        assert!(cfg.is_reachable(join));
+    }
+}
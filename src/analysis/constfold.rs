@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+
+use koopa::ir::builder_traits::ValueBuilder;
+use koopa::ir::{BinaryOp, FunctionData, Value, ValueKind};
+
+use super::manager::FunctionAnalyses;
+use super::pass::FunctionPass;
+use super::rewrite::replace_uses;
+
+/// replaces binary operations on constants with their result, wrapping
+/// around like the backend does
+pub struct ConstFold;
+
+impl FunctionPass for ConstFold {
+    fn name(&self) -> &'static str {
+        "constfold"
+    }
+
+    fn preserves_cfg(&self) -> bool {
+        true
+    }
+
+    fn run_on_function(&mut self, func: &mut FunctionData, _analyses: &mut FunctionAnalyses) {
+        let mut folded = HashMap::new();
+        let bbs: Vec<_> = func.layout().bbs().keys().copied().collect();
+        for bb in bbs {
+            let node = func.layout().bbs().node(&bb).unwrap();
+            let insts: Vec<_> = node.insts().keys().copied().collect();
+            for inst in insts {
+                let binary = match func.dfg().value(inst).kind() {
+                    ValueKind::Binary(binary) => binary.clone(),
+                    _ => continue,
+                };
+                let lhs = integer(func, &folded, binary.lhs());
+                let rhs = integer(func, &folded, binary.rhs());
+                if let Some(value) = lhs.zip(rhs).and_then(|(l, r)| fold(binary.op(), l, r)) {
+                    let int = func.dfg_mut().new_value().integer(value);
+                    folded.insert(inst, int);
+                }
+            }
+        }
+
+        replace_uses(func.dfg_mut(), &folded);
+        for &inst in folded.keys() {
+            let bb = func.layout().parent_bb(inst).unwrap();
+            func.layout_mut().bb_mut(bb).insts_mut().remove(&inst);
+            func.dfg_mut().remove_value(inst);
+        }
+    }
+}
+
+fn integer(func: &FunctionData, folded: &HashMap<Value, Value>, value: Value) -> Option<i32> {
+    let value = folded.get(&value).copied().unwrap_or(value);
+    match func.dfg().value(value).kind() {
+        ValueKind::Integer(int) => Some(int.value()),
+        _ => None,
+    }
+}
+
+/// `None` if it depends on the target, like a division by zero
+fn fold(op: BinaryOp, l: i32, r: i32) -> Option<i32> {
+    let value = match op {
+        BinaryOp::NotEq => (l != r) as i32,
+        BinaryOp::Eq => (l == r) as i32,
+        BinaryOp::Gt => (l > r) as i32,
+        BinaryOp::Lt => (l < r) as i32,
+        BinaryOp::Ge => (l >= r) as i32,
+        BinaryOp::Le => (l <= r) as i32,
+        BinaryOp::Add => l.wrapping_add(r),
+        BinaryOp::Sub => l.wrapping_sub(r),
+        BinaryOp::Mul => l.wrapping_mul(r),
+        BinaryOp::Div if r != 0 => l.wrapping_div(r),
+        BinaryOp::Mod if r != 0 => l.wrapping_rem(r),
+        BinaryOp::And => l & r,
+        BinaryOp::Or => l | r,
+        BinaryOp::Xor => l ^ r,
+        BinaryOp::Shl => l.wrapping_shl(r as u32),
+        BinaryOp::Shr => (l as u32).wrapping_shr(r as u32) as i32,
+        BinaryOp::Sar => l.wrapping_shr(r as u32),
+        _ => return None,
+    };
+    Some(value)
+}
+
+#[cfg(test)]
+mod test {
+    use koopa::ir::BinaryOp;
+
+    use super::fold;
+
+    #[test]
+    fn test_fold() {
+        assert_eq!(fold(BinaryOp::Add, i32::MAX, 1), Some(i32::MIN));
+        assert_eq!(fold(BinaryOp::Div, i32::MIN, -1), Some(i32::MIN));
+        assert_eq!(fold(BinaryOp::Mod, -7, 2), Some(-1));
+        assert_eq!(fold(BinaryOp::Div, 1, 0), None);
+        assert_eq!(fold(BinaryOp::Mod, 1, 0), None);
+        assert_eq!(fold(BinaryOp::Le, 2, 2), Some(1));
+        assert_eq!(fold(BinaryOp::Shr, -1, 28), Some(15));
+        assert_eq!(fold(BinaryOp::Sar, -16, 2), Some(-4));
+    }
+}
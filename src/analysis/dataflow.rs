@@ -0,0 +1,168 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use koopa::ir::{BasicBlock, FunctionData, Value};
+
+use super::cfg::Cfg;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Forward,
+    Backward,
+}
+
+/// a monotone dataflow problem over the blocks of a function, facts form a
+/// lattice where `meet` combines the facts of joining edges and `top` is
+/// what no edge has said anything about yet
+pub trait Analysis {
+    type Fact: Clone + PartialEq;
+
+    const DIRECTION: Direction;
+
+    /// the fact before the entry of a forward problem, or after the blocks
+    /// without successors of a backward one
+    fn boundary(&self, func: &FunctionData) -> Self::Fact;
+
+    fn top(&self, func: &FunctionData) -> Self::Fact;
+
+    fn meet(&self, fact: &mut Self::Fact, other: &Self::Fact);
+
+    /// the effect of `inst`, in the direction of the problem
+    fn transfer(&self, func: &FunctionData, inst: Value, fact: &mut Self::Fact);
+
+    /// the effect of the params of `bb`, which are defined before its first
+    /// instruction
+    fn transfer_params(&self, _func: &FunctionData, _bb: BasicBlock, _fact: &mut Self::Fact) {}
+}
+
+/// the fixed point of an analysis, at the edges of every block and around
+/// every instruction, unreachable blocks included
+pub struct Solution<F> {
+    // at the top of each block, before its params are defined, and at its
+    // bottom
+    block_in: HashMap<BasicBlock, F>,
+    block_out: HashMap<BasicBlock, F>,
+    inst_in: HashMap<Value, F>,
+    inst_out: HashMap<Value, F>,
+}
+
+impl<F> Solution<F> {
+    pub fn entry(&self, bb: BasicBlock) -> &F {
+        &self.block_in[&bb]
+    }
+
+    pub fn exit(&self, bb: BasicBlock) -> &F {
+        &self.block_out[&bb]
+    }
+
+    pub fn before(&self, inst: Value) -> &F {
+        &self.inst_in[&inst]
+    }
+
+    pub fn after(&self, inst: Value) -> &F {
+        &self.inst_out[&inst]
+    }
+}
+
+/// solves `analysis` with a worklist, blocks are taken in reverse postorder
+/// for forward problems and in postorder for backward ones
+pub fn solve<A: Analysis>(analysis: &A, func: &FunctionData, cfg: &Cfg) -> Solution<A::Fact> {
+    let insts: HashMap<_, Vec<_>> = func
+        .layout()
+        .bbs()
+        .iter()
+        .map(|(&bb, node)| (bb, node.insts().keys().copied().collect()))
+        .collect();
+    let mut order = cfg.rpo().to_vec();
+    order.extend(func.layout().bbs().keys().filter(|&&bb| !cfg.is_reachable(bb)));
+    if A::DIRECTION == Direction::Backward {
+        order.reverse();
+    }
+
+    let top = analysis.top(func);
+    let mut block_in: HashMap<_, _> = order.iter().map(|&bb| (bb, top.clone())).collect();
+    let mut block_out = block_in.clone();
+    let mut worklist: VecDeque<_> = order.iter().copied().collect();
+    let mut queued: HashSet<_> = order.iter().copied().collect();
+    while let Some(bb) = worklist.pop_front() {
+        queued.remove(&bb);
+        let next = match A::DIRECTION {
+            Direction::Forward => {
+                let mut fact = if bb == cfg.entry() {
+                    analysis.boundary(func)
+                } else {
+                    top.clone()
+                };
+                for pred in cfg.preds(bb) {
+                    analysis.meet(&mut fact, &block_out[pred]);
+                }
+                block_in.insert(bb, fact.clone());
+                analysis.transfer_params(func, bb, &mut fact);
+                for &inst in &insts[&bb] {
+                    analysis.transfer(func, inst, &mut fact);
+                }
+                if block_out[&bb] == fact {
+                    continue;
+                }
+                block_out.insert(bb, fact);
+                cfg.succs(bb)
+            }
+            Direction::Backward => {
+                let mut fact = if cfg.succs(bb).is_empty() {
+                    analysis.boundary(func)
+                } else {
+                    top.clone()
+                };
+                for succ in cfg.succs(bb) {
+                    analysis.meet(&mut fact, &block_in[succ]);
+                }
+                block_out.insert(bb, fact.clone());
+                for &inst in insts[&bb].iter().rev() {
+                    analysis.transfer(func, inst, &mut fact);
+                }
+                analysis.transfer_params(func, bb, &mut fact);
+                if block_in[&bb] == fact {
+                    continue;
+                }
+                block_in.insert(bb, fact);
+                cfg.preds(bb)
+            }
+        };
+        for &bb in next {
+            if queued.insert(bb) {
+                worklist.push_back(bb);
+            }
+        }
+    }
+
+    // the facts around instructions come from one more walk of each block
+    let mut inst_in = HashMap::new();
+    let mut inst_out = HashMap::new();
+    for &bb in &order {
+        match A::DIRECTION {
+            Direction::Forward => {
+                let mut fact = block_in[&bb].clone();
+                analysis.transfer_params(func, bb, &mut fact);
+                for &inst in &insts[&bb] {
+                    inst_in.insert(inst, fact.clone());
+                    analysis.transfer(func, inst, &mut fact);
+                    inst_out.insert(inst, fact.clone());
+                }
+            }
+            Direction::Backward => {
+                let mut fact = block_out[&bb].clone();
+                for &inst in insts[&bb].iter().rev() {
+                    inst_out.insert(inst, fact.clone());
+                    analysis.transfer(func, inst, &mut fact);
+                    inst_in.insert(inst, fact.clone());
+                }
+            }
+        }
+    }
+
+    Solution {
+        block_in,
+        block_out,
+        inst_in,
+        inst_out,
+    }
+}
@@ -0,0 +1,56 @@
+use koopa::ir::{FunctionData, Value, ValueKind};
+
+use super::manager::FunctionAnalyses;
+use super::pass::FunctionPass;
+
+/// removes instructions whose result is never used and which do nothing
+/// else, `call` and `store` always stay
+pub struct Dce;
+
+impl FunctionPass for Dce {
+    fn name(&self) -> &'static str {
+        "dce"
+    }
+
+    fn preserves_cfg(&self) -> bool {
+        true
+    }
+
+    fn run_on_function(&mut self, func: &mut FunctionData, _analyses: &mut FunctionAnalyses) {
+        let mut worklist: Vec<_> = func
+            .layout()
+            .bbs()
+            .iter()
+            .flat_map(|(_, node)| node.insts().keys())
+            .copied()
+            .collect();
+        while let Some(inst) = worklist.pop() {
+            if !is_dead(func, inst) {
+                continue;
+            }
+            let operands = func.dfg().value(inst).kind().value_uses().collect::<Vec<_>>();
+            let bb = func.layout().parent_bb(inst).unwrap();
+            func.layout_mut().bb_mut(bb).insts_mut().remove(&inst);
+            func.dfg_mut().remove_value(inst);
+            // what they compute may be dead now
+            worklist.extend(operands.into_iter().filter(|&v| func.layout().parent_bb(v).is_some()));
+        }
+    }
+}
+
+fn is_dead(func: &FunctionData, inst: Value) -> bool {
+    // an operand may be removed already while still in the worklist
+    if !func.dfg().values().contains_key(&inst) {
+        return false;
+    }
+    let data = func.dfg().value(inst);
+    data.used_by().is_empty()
+        && matches!(
+            data.kind(),
+            ValueKind::Alloc(_)
+                | ValueKind::Load(_)
+                | ValueKind::GetPtr(_)
+                | ValueKind::GetElemPtr(_)
+                | ValueKind::Binary(_)
+        )
+}
@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+
+use koopa::ir::BasicBlock;
+
+use super::cfg::Cfg;
+
+/// dominator tree of the reachable blocks, after Cooper, Harvey and Kennedy
+/// "A Simple, Fast Dominance Algorithm"
+pub struct DomTree {
+    // immediate dominator, the entry has none
+    #[cfg(test)]
+    idom: HashMap<BasicBlock, BasicBlock>,
+    #[cfg(test)]
+    entry: BasicBlock,
+    children: HashMap<BasicBlock, Vec<BasicBlock>>,
+    frontiers: HashMap<BasicBlock, Vec<BasicBlock>>,
+}
+
+impl DomTree {
+    pub fn new(cfg: &Cfg) -> Self {
+        let index = |bb| cfg.rpo_index(bb).unwrap();
+        let entry = cfg.entry();
+        // while solving, the entry is its own dominator
+        let mut idom = HashMap::from([(entry, entry)]);
+        let intersect = |idom: &HashMap<_, _>, mut a: BasicBlock, mut b: BasicBlock| {
+            while a != b {
+                while index(a) > index(b) {
+                    a = idom[&a];
+                }
+                while index(b) > index(a) {
+                    b = idom[&b];
+                }
+            }
+            a
+        };
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &bb in &cfg.rpo()[1..] {
+                let new_idom = cfg
+                    .preds(bb)
+                    .iter()
+                    .filter(|pred| idom.contains_key(pred))
+                    .copied()
+                    .reduce(|a, b| intersect(&idom, a, b))
+                    .unwrap();
+                if idom.insert(bb, new_idom) != Some(new_idom) {
+                    changed = true;
+                }
+            }
+        }
+        idom.remove(&entry);
+
+        let mut children: HashMap<_, Vec<_>> = HashMap::new();
+        for &bb in cfg.rpo() {
+            if let Some(&parent) = idom.get(&bb) {
+                children.entry(parent).or_default().push(bb);
+            }
+        }
+
+        // a join is in the frontier of each block from its predecessors up
+        // to, not including, its immediate dominator
+        let mut frontiers: HashMap<_, Vec<_>> = HashMap::new();
+        for &bb in cfg.rpo() {
+            let preds: Vec<_> = cfg.preds(bb).iter().filter(|&&p| cfg.is_reachable(p)).collect();
+            if preds.len() < 2 {
+                continue;
+            }
+            for &pred in preds {
+                let mut runner = pred;
+                while Some(&runner) != idom.get(&bb) {
+                    let frontier = frontiers.entry(runner).or_default();
+                    if !frontier.contains(&bb) {
+                        frontier.push(bb);
+                    }
+                    match idom.get(&runner) {
+                        Some(&up) => runner = up,
+                        None => break,
+                    }
+                }
+            }
+        }
+
+        DomTree {
+            #[cfg(test)]
+            idom,
+            #[cfg(test)]
+            entry,
+            children,
+            frontiers,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn idom(&self, bb: BasicBlock) -> Option<BasicBlock> {
+        self.idom.get(&bb).copied()
+    }
+
+    /// whether every path from the entry to `b` goes through `a`, a block
+    /// dominates itself, unreachable blocks are dominated by none
+    #[cfg(test)]
+    pub fn dominates(&self, a: BasicBlock, mut b: BasicBlock) -> bool {
+        if b != self.entry && !self.idom.contains_key(&b) {
+            return false;
+        }
+        loop {
+            if a == b {
+                return true;
+            }
+            match self.idom(b) {
+                Some(up) => b = up,
+                None => return false,
+            }
+        }
+    }
+
+    /// blocks immediately dominated by `bb`, in reverse postorder
+    pub fn children(&self, bb: BasicBlock) -> &[BasicBlock] {
+        self.children.get(&bb).map_or(&[], Vec::as_slice)
+    }
+
+    /// blocks where the dominance of `bb` ends
+    pub fn frontier(&self, bb: BasicBlock) -> &[BasicBlock] {
+        self.frontiers.get(&bb).map_or(&[], Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use koopa::ir::builder_traits::{BasicBlockBuilder, LocalInstBuilder};
+
+    use super::DomTree;
+    use crate::analysis::cfg::Cfg;
+    use crate::analysis::test_util::{bb, only_func, parse, DIAMOND_IN_LOOP};
+
+    #[test]
+    fn diamond_in_loop() {
+        let program = parse(DIAMOND_IN_LOOP);
+        let func = only_func(&program);
+        let dom = DomTree::new(&Cfg::new(func));
+        let [entry, cond, then, left, right, join, end] =
+            ["entry", "cond", "then", "left", "right", "join", "end"].map(|name| bb(func, name));
+
+        assert_eq!(dom.idom(entry), None);
+        assert_eq!(dom.idom(cond), Some(entry));
+        assert_eq!(dom.idom(join), Some(then));
+        assert_eq!(dom.idom(end), Some(cond));
+        let mut children = dom.children(then).to_vec();
+        children.sort_by_key(|&bb| [left, right, join].iter().position(|&b| b == bb));
+        assert_eq!(children, [left, right, join]);
+
+        assert!(dom.dominates(cond, join));
+        assert!(dom.dominates(join, join));
+        assert!(!dom.dominates(left, join));
+        assert!(!dom.dominates(join, cond));
+
+        assert_eq!(dom.frontier(entry), []);
+        assert_eq!(dom.frontier(left), [join]);
+        assert_eq!(dom.frontier(right), [join]);
+        assert_eq!(dom.frontier(then), [cond]);
+        assert_eq!(dom.frontier(join), [cond]);
+        assert_eq!(dom.frontier(cond), [cond]);
+        assert_eq!(dom.frontier(end), []);
+    }
+
+    #[test]
+    fn unreachable_block() {
+        let mut program = parse(
+            r"
+fun @f(): i32 {
+%entry:
+  jump %end
+%end:
+  ret 0
+}",
+        );
+        let f = program.func_layout()[0];
+        let func = program.func_mut(f);
+        let end = bb(func, "end");
+        // the parser drops unreachable blocks, this one is added by hand
+        let dead = func.dfg_mut().new_bb().basic_block(Some("%dead".to_string()));
+        let jump = func.dfg_mut().new_value().jump(end);
+        func.layout_mut().bbs_mut().push_key_back(dead).unwrap();
+        func.layout_mut().bb_mut(dead).insts_mut().push_key_back(jump).unwrap();
+        let func = only_func(&program);
+        let dom = DomTree::new(&Cfg::new(func));
+        let entry = bb(func, "entry");
+
+        assert_eq!(dom.idom(end), Some(entry));
+        assert_eq!(dom.idom(dead), None);
+        assert!(dom.dominates(entry, end));
+        assert!(!dom.dominates(dead, end));
+        assert!(!dom.dominates(dead, dead));
+        assert!(!dom.dominates(entry, dead));
+        assert_eq!(dom.children(entry), [end]);
+        assert_eq!(dom.frontier(dead), []);
+    }
+}
@@ -0,0 +1,461 @@
+use std::collections::{HashMap, HashSet};
+
+use koopa::back::KoopaGenerator;
+use koopa::ir::{BasicBlock, FunctionData, Program, Value, ValueKind};
+
+use super::cfg::Cfg;
+use super::dataflow::{solve, Analysis, Direction};
+use super::rewrite::for_each_operand;
+
+/// the live interval of a value, from its definition to its last use in the
+/// linear order of the function, holes are not tracked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValueLife {
+    pub val: Value,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// live values of a function with a body, only values which may sit in a
+/// register are tracked: function and block params and the results of
+/// instructions other than `alloc`
+///
+/// the linear order numbers the blocks in layout order, each block takes a
+/// position for its params and then one for each instruction
+pub struct LiveVariables {
+    block_in: HashMap<BasicBlock, HashSet<Value>>,
+    #[cfg(test)]
+    block_out: HashMap<BasicBlock, HashSet<Value>>,
+    // live before and after each instruction
+    #[cfg(test)]
+    inst_in: HashMap<Value, HashSet<Value>>,
+    inst_out: HashMap<Value, HashSet<Value>>,
+    positions: HashMap<Value, usize>,
+    // positions of the params and the terminator of each block
+    #[cfg(test)]
+    block_ranges: HashMap<BasicBlock, (usize, usize)>,
+    // sorted by start
+    lives: Vec<ValueLife>,
+}
+
+impl LiveVariables {
+    pub fn new(func: &FunctionData, cfg: &Cfg) -> Self {
+        let solution = solve(&Liveness, func, cfg);
+        let bbs: Vec<_> = func.layout().bbs().keys().copied().collect();
+        let block_in: HashMap<_, _> =
+            bbs.iter().map(|&bb| (bb, solution.entry(bb).clone())).collect();
+        let block_out: HashMap<_, _> =
+            bbs.iter().map(|&bb| (bb, solution.exit(bb).clone())).collect();
+        let insts = bbs
+            .iter()
+            .flat_map(|&bb| func.layout().bbs().node(&bb).unwrap().insts().keys());
+        #[cfg(test)]
+        let mut inst_in = HashMap::new();
+        let mut inst_out = HashMap::new();
+        for &inst in insts {
+            #[cfg(test)]
+            inst_in.insert(inst, solution.before(inst).clone());
+            inst_out.insert(inst, solution.after(inst).clone());
+        }
+
+        let mut positions = HashMap::new();
+        let mut block_ranges = HashMap::new();
+        let mut pos = 0;
+        for &bb in &bbs {
+            let start = pos;
+            for &inst in func.layout().bbs().node(&bb).unwrap().insts().keys() {
+                pos += 1;
+                positions.insert(inst, pos);
+            }
+            block_ranges.insert(bb, (start, pos));
+            pos += 1;
+        }
+
+        // values in order of definition, the sort below keeps it for ties
+        let mut order: Vec<_> = func.params().to_vec();
+        let mut ranges = HashMap::new();
+        for &value in func.params() {
+            ranges.insert(value, (0, 0));
+        }
+        let extend = |ranges: &mut HashMap<_, (usize, usize)>, value, pos| {
+            let range = ranges.entry(value).or_insert((pos, pos));
+            range.0 = range.0.min(pos);
+            range.1 = range.1.max(pos);
+        };
+        for &bb in &bbs {
+            let (start, end) = block_ranges[&bb];
+            for &param in func.dfg().bb(bb).params() {
+                order.push(param);
+                extend(&mut ranges, param, start);
+            }
+            for &value in &block_in[&bb] {
+                extend(&mut ranges, value, start);
+            }
+            for &value in &block_out[&bb] {
+                extend(&mut ranges, value, end);
+            }
+            for &inst in func.layout().bbs().node(&bb).unwrap().insts().keys() {
+                let pos = positions[&inst];
+                if is_tracked(func, inst) {
+                    order.push(inst);
+                    extend(&mut ranges, inst, pos);
+                }
+                for value in operands(func, inst) {
+                    extend(&mut ranges, value, pos);
+                }
+            }
+        }
+        let mut lives: Vec<_> = order
+            .into_iter()
+            .map(|val| {
+                let (start, end) = ranges[&val];
+                ValueLife { val, start, end }
+            })
+            .collect();
+        lives.sort_by_key(|life| life.start);
+
+        LiveVariables {
+            block_in,
+            #[cfg(test)]
+            block_out,
+            #[cfg(test)]
+            inst_in,
+            inst_out,
+            positions,
+            #[cfg(test)]
+            block_ranges,
+            lives,
+        }
+    }
+
+    /// live at the start of `bb`, its params are not
+    pub fn live_in(&self, bb: BasicBlock) -> &HashSet<Value> {
+        &self.block_in[&bb]
+    }
+
+    #[cfg(test)]
+    pub fn live_out(&self, bb: BasicBlock) -> &HashSet<Value> {
+        &self.block_out[&bb]
+    }
+
+    /// live right before `inst`, its operands are
+    #[cfg(test)]
+    pub fn live_before(&self, inst: Value) -> &HashSet<Value> {
+        &self.inst_in[&inst]
+    }
+
+    /// live right after `inst`, its result is if it's used later
+    pub fn live_after(&self, inst: Value) -> &HashSet<Value> {
+        &self.inst_out[&inst]
+    }
+
+    /// position of `inst` in the linear order
+    pub fn position(&self, inst: Value) -> usize {
+        self.positions[&inst]
+    }
+
+    /// positions of the params and of the last instruction of `bb`
+    #[cfg(test)]
+    pub fn block_range(&self, bb: BasicBlock) -> (usize, usize) {
+        self.block_ranges[&bb]
+    }
+
+    /// a live interval for every tracked value, by increasing start
+    pub fn lives(&self) -> &[ValueLife] {
+        &self.lives
+    }
+}
+
+/// live in = uses + (live out - defs), live out = live in of the
+/// successors, block args are used by the terminator of the predecessor
+/// and block params are defs of their block
+struct Liveness;
+
+impl Analysis for Liveness {
+    type Fact = HashSet<Value>;
+
+    const DIRECTION: Direction = Direction::Backward;
+
+    fn boundary(&self, _func: &FunctionData) -> Self::Fact {
+        HashSet::new()
+    }
+
+    fn top(&self, _func: &FunctionData) -> Self::Fact {
+        HashSet::new()
+    }
+
+    fn meet(&self, fact: &mut Self::Fact, other: &Self::Fact) {
+        fact.extend(other);
+    }
+
+    fn transfer(&self, func: &FunctionData, inst: Value, fact: &mut Self::Fact) {
+        fact.remove(&inst);
+        fact.extend(operands(func, inst));
+    }
+
+    fn transfer_params(&self, func: &FunctionData, bb: BasicBlock, fact: &mut Self::Fact) {
+        for param in func.dfg().bb(bb).params() {
+            fact.remove(param);
+        }
+    }
+}
+
+/// whether the result of `value` is tracked, see `LiveVariables`
+fn is_tracked(func: &FunctionData, value: Value) -> bool {
+    if value.is_global() {
+        return false;
+    }
+    let data = func.dfg().value(value);
+    match data.kind() {
+        ValueKind::FuncArgRef(_) | ValueKind::BlockArgRef(_) => true,
+        ValueKind::Load(_)
+        | ValueKind::GetPtr(_)
+        | ValueKind::GetElemPtr(_)
+        | ValueKind::Binary(_)
+        | ValueKind::Call(_) => !data.ty().is_unit(),
+        _ => false,
+    }
+}
+
+/// the tracked operands of `inst`
+fn operands(func: &FunctionData, inst: Value) -> Vec<Value> {
+    let mut data = func.dfg().value(inst).clone();
+    let mut operands = vec![];
+    for_each_operand(&mut data, |value| {
+        if is_tracked(func, *value) && !operands.contains(value) {
+            operands.push(*value);
+        }
+    });
+    operands
+}
+
+/// the text koopa prints for `program` with the live values of each function
+/// in comments: live in after block labels and live after each instruction,
+/// the values and blocks are named first so that the comments can use the
+/// names the printer prints
+pub fn annotate(program: &mut Program) -> String {
+    let funcs: Vec<_> = program
+        .func_layout()
+        .iter()
+        .copied()
+        .filter(|&func| program.func(func).layout().entry_bb().is_some())
+        .collect();
+    let mut globals: HashSet<_> = program
+        .func_layout()
+        .iter()
+        .map(|&func| program.func(func).name().to_string())
+        .collect();
+    for &value in program.inst_layout() {
+        globals.extend(program.borrow_value(value).name().clone());
+    }
+    for &func in &funcs {
+        name_all(program.func_mut(func), &globals);
+    }
+    let mut gen = KoopaGenerator::new(vec![]);
+    gen.generate_on(program).unwrap();
+    let koopa = String::from_utf8(gen.writer()).unwrap();
+
+    let mut annotated = String::new();
+    let mut lines = koopa.lines();
+    for &func in &funcs {
+        let func = program.func(func);
+        for line in lines.by_ref() {
+            push_line(&mut annotated, line, None);
+            if line.starts_with("fun ") {
+                break;
+            }
+        }
+        let live = LiveVariables::new(func, &Cfg::new(func));
+        let rank: HashMap<_, _> =
+            live.lives().iter().enumerate().map(|(i, life)| (life.val, i)).collect();
+        let set = |values: &HashSet<Value>| {
+            let mut values: Vec<_> = values.iter().collect();
+            values.sort_by_key(|&value| rank[value]);
+            let names: Vec<_> = values
+                .iter()
+                .map(|&&value| func.dfg().value(value).name().as_deref().unwrap())
+                .collect();
+            format!("{{{}}}", names.join(", "))
+        };
+        // blocks are apart by empty lines
+        let mut next_line = |annotated: &mut String| loop {
+            match lines.next().unwrap() {
+                "" => annotated.push('\n'),
+                line => return line,
+            }
+        };
+        for (&bb, node) in func.layout().bbs() {
+            let comment = format!("live in: {}", set(live.live_in(bb)));
+            let line = next_line(&mut annotated);
+            push_line(&mut annotated, line, Some(comment));
+            for &inst in node.insts().keys() {
+                let comment = format!("live: {}", set(live.live_after(inst)));
+                let line = next_line(&mut annotated);
+                push_line(&mut annotated, line, Some(comment));
+            }
+        }
+    }
+    for line in lines {
+        push_line(&mut annotated, line, None);
+    }
+    annotated
+}
+
+/// names the values and blocks of `func` which the printer would name
+/// itself, in the order it prints them: unnamed ones are numbered and a name
+/// taken already gets a suffix, so the printer keeps every name as it is
+fn name_all(func: &mut FunctionData, globals: &HashSet<String>) {
+    let mut values = func.params().to_vec();
+    let mut bbs = vec![];
+    for (&bb, node) in func.layout().bbs() {
+        bbs.push(bb);
+        values.extend(func.dfg().bb(bb).params());
+        let insts = node.insts().keys().copied();
+        values.extend(insts.filter(|&inst| !func.dfg().value(inst).ty().is_unit()));
+    }
+
+    let mut taken = globals.clone();
+    for value in values {
+        let name = unique_name(func.dfg().value(value).name(), &mut taken);
+        func.dfg_mut().set_value_name(value, Some(name));
+    }
+    let mut taken = HashSet::new();
+    for bb in bbs {
+        let name = unique_name(func.dfg().bb(bb).name(), &mut taken);
+        func.dfg_mut().bb_mut(bb).set_name(Some(name));
+    }
+}
+
+/// `name`, or `name_0`, `name_1` and so on if it's taken, `%0`, `%1` and so
+/// on for no name
+fn unique_name(name: &Option<String>, taken: &mut HashSet<String>) -> String {
+    let name = match name {
+        Some(name) if !taken.contains(name) => name.clone(),
+        Some(name) => (0..)
+            .map(|i| format!("{}_{}", name, i))
+            .find(|name| !taken.contains(name))
+            .unwrap(),
+        None => (0..)
+            .map(|i| format!("%{}", i))
+            .find(|name| !taken.contains(name))
+            .unwrap(),
+    };
+    taken.insert(name.clone());
+    name
+}
+
+fn push_line(text: &mut String, line: &str, comment: Option<String>) {
+    text.push_str(line);
+    if let Some(comment) = comment {
+        text.push_str(" // ");
+        text.push_str(&comment);
+    }
+    text.push('\n');
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use koopa::ir::Value;
+
+    use super::{annotate, LiveVariables, ValueLife};
+    use crate::analysis::cfg::Cfg;
+    use crate::analysis::test_util::{bb, only_func, parse, value};
+
+    const LOOP: &str = r"
+fun @f(@n: i32): i32 {
+%entry:
+  %m = add @n, 1
+  jump %cond(0, 0)
+%cond(%i: i32, %s: i32):
+  %lt = lt %i, @n
+  br %lt, %body, %end
+%body:
+  %s2 = add %s, %i
+  %i2 = add %i, 1
+  jump %cond(%i2, %s2)
+%end:
+  %r = add %s, %m
+  ret %r
+}";
+
+    #[test]
+    fn loop_sets() {
+        let program = parse(LOOP);
+        let func = only_func(&program);
+        let live = LiveVariables::new(func, &Cfg::new(func));
+        let set = |names: &[&str]| -> HashSet<Value> {
+            names.iter().map(|&name| value(func, name)).collect()
+        };
+
+        assert_eq!(live.live_in(bb(func, "entry")), &set(&["@n"]));
+        assert_eq!(live.live_out(bb(func, "entry")), &set(&["@n", "%m"]));
+        // params are defined by their block
+        assert_eq!(live.live_in(bb(func, "cond")), &set(&["@n", "%m"]));
+        assert_eq!(live.live_in(bb(func, "body")), &set(&["@n", "%m", "%i", "%s"]));
+        assert_eq!(live.live_out(bb(func, "body")), &set(&["@n", "%m"]));
+        assert_eq!(live.live_in(bb(func, "end")), &set(&["%m", "%s"]));
+
+        let lt = value(func, "%lt");
+        assert_eq!(live.live_before(lt), &set(&["@n", "%m", "%i", "%s"]));
+        assert_eq!(live.live_after(lt), &set(&["@n", "%m", "%i", "%s", "%lt"]));
+        let sum = value(func, "%r");
+        assert_eq!(live.live_after(sum), &set(&["%r"]));
+    }
+
+    #[test]
+    fn loop_intervals() {
+        let program = parse(LOOP);
+        let func = only_func(&program);
+        let live = LiveVariables::new(func, &Cfg::new(func));
+        // entry: 0 params, 1 %m, 2 jump; cond: 3 params, 4 %lt, 5 br;
+        // body: 6, 7 %s2, 8 %i2, 9 jump; end: 10, 11 %r, 12 ret
+        assert_eq!(live.block_range(bb(func, "body")), (6, 9));
+        assert_eq!(live.position(value(func, "%i2")), 8);
+        let life = |name| *live.lives().iter().find(|l| l.val == value(func, name)).unwrap();
+        let expect = |name, start, end| ValueLife {
+            val: value(func, name),
+            start,
+            end,
+        };
+        assert_eq!(life("@n"), expect("@n", 0, 9));
+        assert_eq!(life("%m"), expect("%m", 1, 11));
+        assert_eq!(life("%i"), expect("%i", 3, 8));
+        assert_eq!(life("%s"), expect("%s", 3, 11));
+        assert_eq!(life("%lt"), expect("%lt", 4, 5));
+        assert_eq!(life("%i2"), expect("%i2", 8, 9));
+        assert_eq!(life("%r"), expect("%r", 11, 12));
+        assert!(live.lives().windows(2).all(|w| w[0].start <= w[1].start));
+    }
+
+    #[test]
+    fn annotated_koopa() {
+        let mut program = parse(LOOP);
+        let annotated = annotate(&mut program);
+        assert!(annotated.contains("%cond(%i: i32, %s: i32): // live in: {@n, %m}\n"));
+        assert!(annotated.contains("  %lt = lt %i, @n // live: {@n, %m, %i, %s, %lt}\n"));
+        assert!(annotated.contains("  ret %r // live: {}\n"));
+        // only comments are added
+        assert_eq!(parse(&annotated).func_layout().len(), 1);
+    }
+
+    #[test]
+    fn annotated_names() {
+        // a value without a name and one whose name is taken
+        let mut program = parse(LOOP);
+        let (m, s) = {
+            let func = only_func(&program);
+            (value(func, "%m"), value(func, "%s"))
+        };
+        let f = program.func_layout()[0];
+        let dfg = program.func_mut(f).dfg_mut();
+        dfg.set_value_name(m, None);
+        dfg.set_value_name(s, Some("%i".to_string()));
+        let annotated = annotate(&mut program);
+        assert!(annotated.contains("%cond(%i: i32, %i_0: i32): // live in: {@n, %0}\n"));
+        assert!(annotated.contains("  %lt = lt %i, @n // live: {@n, %0, %i, %i_0, %lt}\n"));
+        assert!(annotated.contains("  %r = add %i_0, %0 // live: {%r}\n"));
+        assert_eq!(parse(&annotated).func_layout().len(), 1);
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::{HashMap, HashSet};
+
+use koopa::ir::BasicBlock;
+
+use super::cfg::Cfg;
+use super::dominators::DomTree;
+
+/// a natural loop, the blocks that reach a back edge to `header` without
+/// going through it, loops with the same header are one loop
+pub struct Loop {
+    header: BasicBlock,
+    blocks: HashSet<BasicBlock>,
+    // sources of the back edges
+    latches: Vec<BasicBlock>,
+    // index in `LoopNest::loops` of the innermost loop around this one
+    parent: Option<usize>,
+    depth: usize,
+}
+
+impl Loop {
+    pub fn header(&self) -> BasicBlock {
+        self.header
+    }
+
+    pub fn contains(&self, bb: BasicBlock) -> bool {
+        self.blocks.contains(&bb)
+    }
+
+    pub fn blocks(&self) -> &HashSet<BasicBlock> {
+        &self.blocks
+    }
+
+    pub fn latches(&self) -> &[BasicBlock] {
+        &self.latches
+    }
+
+    /// 1 for an outermost loop
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+}
+
+/// the natural loops of a function and how they nest
+pub struct LoopNest {
+    // in reverse postorder of their headers, so outer loops come first
+    loops: Vec<Loop>,
+    innermost: HashMap<BasicBlock, usize>,
+}
+
+impl LoopNest {
+    pub fn new(cfg: &Cfg, dom: &DomTree) -> Self {
+        let mut loops: Vec<Loop> = vec![];
+        for &header in cfg.rpo() {
+            let latches: Vec<_> = cfg
+                .preds(header)
+                .iter()
+                .copied()
+                .filter(|&pred| dom.dominates(header, pred))
+                .collect();
+            if latches.is_empty() {
+                continue;
+            }
+            let mut blocks = HashSet::from([header]);
+            let mut worklist = latches.clone();
+            while let Some(bb) = worklist.pop() {
+                if blocks.insert(bb) {
+                    worklist.extend(cfg.preds(bb).iter().filter(|&&p| cfg.is_reachable(p)));
+                }
+            }
+            // loops around this one have come already, the smallest is the
+            // innermost
+            let parent = (0..loops.len())
+                .filter(|&i| loops[i].contains(header))
+                .min_by_key(|&i| loops[i].blocks.len());
+            let depth = parent.map_or(1, |i| loops[i].depth + 1);
+            loops.push(Loop {
+                header,
+                blocks,
+                latches,
+                parent,
+                depth,
+            });
+        }
+
+        let mut innermost = HashMap::new();
+        for (i, l) in loops.iter().enumerate() {
+            for &bb in &l.blocks {
+                let inner = innermost.entry(bb).or_insert(i);
+                if loops[*inner].depth < l.depth {
+                    *inner = i;
+                }
+            }
+        }
+        LoopNest { loops, innermost }
+    }
+
+    /// outer loops come before the loops in them
+    pub fn loops(&self) -> &[Loop] {
+        &self.loops
+    }
+
+    pub fn innermost(&self, bb: BasicBlock) -> Option<&Loop> {
+        self.innermost.get(&bb).map(|&i| &self.loops[i])
+    }
+
+    pub fn parent(&self, l: &Loop) -> Option<&Loop> {
+        l.parent.map(|i| &self.loops[i])
+    }
+
+    /// how many loops `bb` is in
+    pub fn depth(&self, bb: BasicBlock) -> usize {
+        self.innermost(bb).map_or(0, Loop::depth)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use super::LoopNest;
+    use crate::analysis::cfg::Cfg;
+    use crate::analysis::dominators::DomTree;
+    use crate::analysis::test_util::{bb, only_func, parse};
+
+    #[test]
+    fn nested_loops() {
+        // the inner loop has a `continue`, so two back edges go to its header
+        let program = parse(
+            r"
+fun @f(@n: i32): i32 {
+%entry:
+  jump %outer
+%outer:
+  br @n, %inner, %end
+%inner:
+  br @n, %body, %outer_latch
+%body:
+  br @n, %inner, %cont
+%cont:
+  jump %inner
+%outer_latch:
+  jump %outer
+%end:
+  ret 0
+}",
+        );
+        let func = only_func(&program);
+        let cfg = Cfg::new(func);
+        let loops = LoopNest::new(&cfg, &DomTree::new(&cfg));
+        let [entry, outer, inner, body, cont, outer_latch, end] =
+            ["entry", "outer", "inner", "body", "cont", "outer_latch", "end"]
+                .map(|name| bb(func, name));
+
+        assert_eq!(loops.loops().len(), 2);
+        let outer_loop = &loops.loops()[0];
+        assert_eq!(outer_loop.header(), outer);
+        assert_eq!(outer_loop.latches(), [outer_latch]);
+        assert_eq!(
+            outer_loop.blocks(),
+            &HashSet::from([outer, inner, body, cont, outer_latch])
+        );
+        assert!(loops.parent(outer_loop).is_none());
+
+        let inner_loop = &loops.loops()[1];
+        assert_eq!(inner_loop.header(), inner);
+        let latches: HashSet<_> = inner_loop.latches().iter().copied().collect();
+        assert_eq!(latches, HashSet::from([body, cont]));
+        assert_eq!(inner_loop.blocks(), &HashSet::from([inner, body, cont]));
+        assert_eq!(loops.parent(inner_loop).unwrap().header(), outer);
+
+        assert_eq!(loops.depth(entry), 0);
+        assert_eq!(loops.depth(end), 0);
+        assert_eq!(loops.depth(outer), 1);
+        assert_eq!(loops.depth(outer_latch), 1);
+        assert_eq!(loops.depth(inner), 2);
+        assert_eq!(loops.depth(cont), 2);
+        assert_eq!(loops.innermost(body).unwrap().header(), inner);
+        assert!(loops.innermost(end).is_none());
+    }
+}
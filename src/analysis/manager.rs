@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use koopa::ir::{Function, FunctionData};
+
+use super::cfg::Cfg;
+use super::dominators::DomTree;
+#[cfg(test)]
+use super::loops::LoopNest;
+
+/// analyses of one function, computed when first asked for and kept until
+/// a pass changes its blocks or edges
+#[derive(Default)]
+pub struct FunctionAnalyses {
+    cfg: Option<Rc<Cfg>>,
+    dom_tree: Option<Rc<DomTree>>,
+    #[cfg(test)]
+    loops: Option<Rc<LoopNest>>,
+}
+
+impl FunctionAnalyses {
+    pub fn cfg(&mut self, func: &FunctionData) -> Rc<Cfg> {
+        self.cfg.get_or_insert_with(|| Rc::new(Cfg::new(func))).clone()
+    }
+
+    pub fn dom_tree(&mut self, func: &FunctionData) -> Rc<DomTree> {
+        if let Some(dom_tree) = &self.dom_tree {
+            return dom_tree.clone();
+        }
+        let dom_tree = Rc::new(DomTree::new(&self.cfg(func)));
+        self.dom_tree = Some(dom_tree.clone());
+        dom_tree
+    }
+
+    #[cfg(test)]
+    pub fn loops(&mut self, func: &FunctionData) -> Rc<LoopNest> {
+        if let Some(loops) = &self.loops {
+            return loops.clone();
+        }
+        let loops = Rc::new(LoopNest::new(&self.cfg(func), &self.dom_tree(func)));
+        self.loops = Some(loops.clone());
+        loops
+    }
+
+    /// forgets everything, the control flow has changed
+    pub fn invalidate(&mut self) {
+        *self = FunctionAnalyses::default();
+    }
+}
+
+/// the analyses of every function, shared by the passes of a pass manager
+#[derive(Default)]
+pub struct AnalysisManager {
+    functions: HashMap<Function, FunctionAnalyses>,
+}
+
+impl AnalysisManager {
+    pub fn function(&mut self, func: Function) -> &mut FunctionAnalyses {
+        self.functions.entry(func).or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+
+    use super::FunctionAnalyses;
+    use crate::analysis::test_util::{only_func, parse};
+
+    #[test]
+    fn cached_until_invalidated() {
+        let program = parse(
+            r"
+fun @f(): i32 {
+%entry:
+  jump %end
+%end:
+  ret 0
+}",
+        );
+        let func = only_func(&program);
+        let mut analyses = FunctionAnalyses::default();
+        let cfg = analyses.cfg(func);
+        let loops = analyses.loops(func);
+        assert!(Rc::ptr_eq(&cfg, &analyses.cfg(func)));
+        assert!(Rc::ptr_eq(&loops, &analyses.loops(func)));
+        assert!(loops.loops().is_empty());
+
+        analyses.invalidate();
+        assert!(!Rc::ptr_eq(&cfg, &analyses.cfg(func)));
+    }
+}
@@ -0,0 +1,352 @@
+use std::collections::{HashMap, HashSet};
+
+use koopa::ir::builder_traits::ValueBuilder;
+use koopa::ir::{BasicBlock, FunctionData, Type, TypeKind, Value, ValueKind};
+
+use super::cfg::Cfg;
+use super::dominators::DomTree;
+use super::manager::FunctionAnalyses;
+use super::pass::FunctionPass;
+use super::rewrite::{new_block_param, replace_uses};
+
+/// promotes the allocs of `i32` and pointers which are only loaded from and
+/// stored to into SSA values, they are merged by block params placed at the
+/// dominance frontiers of their stores
+pub struct Mem2Reg;
+
+impl FunctionPass for Mem2Reg {
+    fn name(&self) -> &'static str {
+        "mem2reg"
+    }
+
+    fn preserves_cfg(&self) -> bool {
+        true
+    }
+
+    fn run_on_function(&mut self, func: &mut FunctionData, analyses: &mut FunctionAnalyses) {
+        promote(func, analyses);
+    }
+}
+
+fn promote(func: &mut FunctionData, analyses: &mut FunctionAnalyses) {
+    let allocs: Vec<_> = func
+        .layout()
+        .bbs()
+        .iter()
+        .flat_map(|(_, node)| node.insts().keys())
+        .copied()
+        .filter(|&inst| is_promotable(func, inst))
+        .collect();
+    if allocs.is_empty() {
+        return;
+    }
+    let cfg = analyses.cfg(func);
+    let dom = analyses.dom_tree(func);
+
+    let mut renamer = Renamer {
+        params: HashMap::new(),
+        stacks: allocs.iter().map(|&alloc| (alloc, vec![])).collect(),
+        undefs: HashMap::new(),
+        replaced: HashMap::new(),
+        dead: vec![],
+    };
+    for &alloc in &allocs {
+        renamer.place_params(func, &cfg, &dom, alloc);
+    }
+    renamer.rename(func, &dom, cfg.entry());
+    // unreachable blocks only see their own stores
+    let reachable: HashSet<_> = cfg.rpo().iter().collect();
+    let bbs: Vec<_> = func.layout().bbs().keys().copied().collect();
+    for bb in bbs {
+        if !reachable.contains(&bb) {
+            renamer.rename(func, &dom, bb);
+        }
+    }
+
+    replace_uses(func.dfg_mut(), &renamer.replaced);
+    // stores first, they may store a load
+    let (stores, loads): (Vec<_>, Vec<_>) = renamer
+        .dead
+        .into_iter()
+        .partition(|&(_, inst)| matches!(func.dfg().value(inst).kind(), ValueKind::Store(_)));
+    for (bb, inst) in stores.into_iter().chain(loads) {
+        func.layout_mut().bb_mut(bb).insts_mut().remove(&inst);
+        func.dfg_mut().remove_value(inst);
+    }
+    for alloc in allocs {
+        let bb = func.layout().parent_bb(alloc).unwrap();
+        func.layout_mut().bb_mut(bb).insts_mut().remove(&alloc);
+        func.dfg_mut().remove_value(alloc);
+    }
+}
+
+/// a scalar alloc whose address never escapes
+pub fn is_promotable(func: &FunctionData, alloc: Value) -> bool {
+    let data = func.dfg().value(alloc);
+    if !matches!(data.kind(), ValueKind::Alloc(_)) {
+        return false;
+    }
+    if !matches!(pointee(data.ty()).kind(), TypeKind::Int32 | TypeKind::Pointer(_)) {
+        return false;
+    }
+    data.used_by().iter().all(|&user| match func.dfg().value(user).kind() {
+        ValueKind::Load(_) => true,
+        ValueKind::Store(store) => store.dest() == alloc && store.value() != alloc,
+        _ => false,
+    })
+}
+
+fn pointee(ty: &Type) -> Type {
+    match ty.kind() {
+        TypeKind::Pointer(base) => base.clone(),
+        _ => unreachable!("alloc should be a pointer: {}", ty),
+    }
+}
+
+struct Renamer {
+    // params added to each block, with their alloc
+    params: HashMap<BasicBlock, Vec<(Value, Value)>>,
+    // values of each alloc along the path of the dominator tree
+    stacks: HashMap<Value, Vec<Value>>,
+    // what is loaded before any store
+    undefs: HashMap<Value, Value>,
+    // loads and the value they load
+    replaced: HashMap<Value, Value>,
+    // loads and stores to remove, with their block
+    dead: Vec<(BasicBlock, Value)>,
+}
+
+impl Renamer {
+    /// adds params for `alloc` to the iterated dominance frontier of its
+    /// stores, but only if it's loaded in some block before being stored,
+    /// otherwise no value of it ever flows between blocks
+    fn place_params(&mut self, func: &mut FunctionData, cfg: &Cfg, dom: &DomTree, alloc: Value) {
+        let mut stored = vec![];
+        let mut live_in = false;
+        for &bb in cfg.rpo() {
+            let node = func.layout().bbs().node(&bb).unwrap();
+            let mut has_store = false;
+            for &inst in node.insts().keys() {
+                match func.dfg().value(inst).kind() {
+                    ValueKind::Load(load) if load.src() == alloc && !has_store => live_in = true,
+                    ValueKind::Store(store) if store.dest() == alloc => has_store = true,
+                    _ => {}
+                }
+            }
+            if has_store {
+                stored.push(bb);
+            }
+        }
+        if !live_in {
+            return;
+        }
+
+        let ty = pointee(func.dfg().value(alloc).ty());
+        // `@x` gets `%x`, the printer makes names unique
+        let name = func.dfg().value(alloc).name().as_ref().map(|name| format!("%{}", &name[1..]));
+        let mut has_param = HashSet::new();
+        while let Some(bb) = stored.pop() {
+            for &join in dom.frontier(bb) {
+                if !has_param.insert(join) {
+                    continue;
+                }
+                let index = func.dfg().bb(join).params().len();
+                let param = new_block_param(func.dfg_mut(), ty.clone(), index);
+                func.dfg_mut().set_value_name(param, name.clone());
+                func.dfg_mut().bb_mut(join).params_mut().push(param);
+                self.params.entry(join).or_default().push((alloc, param));
+                stored.push(join);
+            }
+        }
+    }
+
+    fn current(&mut self, func: &mut FunctionData, alloc: Value) -> Value {
+        if let Some(&value) = self.stacks[&alloc].last() {
+            return value;
+        }
+        *self.undefs.entry(alloc).or_insert_with(|| {
+            let ty = pointee(func.dfg().value(alloc).ty());
+            func.dfg_mut().new_value().undef(ty)
+        })
+    }
+
+    /// replaces the loads and stores of `bb` and the blocks it dominates
+    fn rename(&mut self, func: &mut FunctionData, dom: &DomTree, bb: BasicBlock) {
+        let mut pushed = vec![];
+        for &(alloc, param) in self.params.get(&bb).into_iter().flatten() {
+            self.stacks.get_mut(&alloc).unwrap().push(param);
+            pushed.push(alloc);
+        }
+
+        let node = func.layout().bbs().node(&bb).unwrap();
+        let insts: Vec<_> = node.insts().keys().copied().collect();
+        for &inst in &insts {
+            match func.dfg().value(inst).kind() {
+                ValueKind::Load(load) if self.stacks.contains_key(&load.src()) => {
+                    let value = self.current(func, load.src());
+                    self.replaced.insert(inst, value);
+                    self.dead.push((bb, inst));
+                }
+                ValueKind::Store(store) if self.stacks.contains_key(&store.dest()) => {
+                    self.stacks.get_mut(&store.dest()).unwrap().push(store.value());
+                    pushed.push(store.dest());
+                    self.dead.push((bb, inst));
+                }
+                _ => {}
+            }
+        }
+        if let Some(&term) = insts.last() {
+            self.pass_args(func, term);
+        }
+
+        for &child in dom.children(bb) {
+            self.rename(func, dom, child);
+        }
+        for alloc in pushed {
+            self.stacks.get_mut(&alloc).unwrap().pop();
+        }
+    }
+
+    /// appends the current values of the allocs to the args of each edge
+    /// going to a block with new params, terminators have no users so their
+    /// data can be replaced as is
+    fn pass_args(&mut self, func: &mut FunctionData, term: Value) {
+        let mut data = func.dfg().value(term).clone();
+        let targets = match data.kind() {
+            ValueKind::Jump(jump) => vec![jump.target()],
+            ValueKind::Branch(br) => vec![br.true_bb(), br.false_bb()],
+            _ => return,
+        };
+        let mut args: Vec<Vec<Value>> = vec![];
+        for target in targets {
+            let params = self.params.get(&target).cloned().unwrap_or_default();
+            args.push(params.iter().map(|&(alloc, _)| self.current(func, alloc)).collect());
+        }
+        if args.iter().all(Vec::is_empty) {
+            return;
+        }
+        match data.kind_mut() {
+            ValueKind::Jump(jump) => jump.args_mut().extend(&args[0]),
+            ValueKind::Branch(br) => {
+                br.true_args_mut().extend(&args[0]);
+                br.false_args_mut().extend(&args[1]);
+            }
+            _ => unreachable!(),
+        }
+        func.dfg_mut().replace_value_with(term).raw(data);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use koopa::back::KoopaGenerator;
+    use koopa::front::Driver;
+
+    use crate::analysis::pass::PassManager;
+
+    fn run(koopa: &str) -> String {
+        let mut program = Driver::from(koopa).generate_program().unwrap();
+        PassManager::with_names(&["mem2reg"]).unwrap().run(&mut program);
+        let mut gen = KoopaGenerator::new(vec![]);
+        gen.generate_on(&program).unwrap();
+        let koopa = String::from_utf8(gen.writer()).unwrap();
+        // the result must be valid koopa again
+        if let Err(e) = Driver::from(koopa.as_str()).generate_program() {
+            panic!("invalid koopa: {:?}\n{}", e, koopa);
+        }
+        koopa
+    }
+
+    #[test]
+    fn loop_and_join() {
+        let koopa = run(r"
+fun @f(@n: i32): i32 {
+%entry:
+  @i = alloc i32
+  @s = alloc i32
+  store 0, @i
+  store 0, @s
+  jump %cond
+%cond:
+  %0 = load @i
+  %1 = lt %0, @n
+  br %1, %body, %end
+%body:
+  %2 = load @i
+  %3 = mod %2, 2
+  br %3, %odd, %next
+%odd:
+  %4 = load @s
+  %5 = add %4, %2
+  store %5, @s
+  jump %next
+%next:
+  %6 = add %2, 1
+  store %6, @i
+  jump %cond
+%end:
+  %7 = load @s
+  ret %7
+}
+");
+        for inst in ["alloc", "load", "store"] {
+            assert!(!koopa.contains(inst), "{} in {}", inst, koopa);
+        }
+        assert!(koopa.contains("%cond(%i: i32, %s: i32)"), "{}", koopa);
+        assert!(koopa.contains("%next(%s_0: i32)"), "{}", koopa);
+        assert!(koopa.contains("jump %cond(0, 0)"), "{}", koopa);
+    }
+
+    #[test]
+    fn escaped_and_local() {
+        // @a is passed to a call, @t never lives across blocks
+        let koopa = run(r"
+decl @g(*i32)
+
+fun @f(): i32 {
+%entry:
+  @a = alloc i32
+  @t = alloc i32
+  @arr = alloc [i32, 2]
+  store 1, @a
+  call @g(@a)
+  store 2, @t
+  %0 = load @t
+  jump %end
+%end:
+  %1 = load @a
+  %2 = add %0, %1
+  ret %2
+}
+");
+        assert!(koopa.contains("@a = alloc i32"), "{}", koopa);
+        assert!(koopa.contains("@arr = alloc [i32, 2]"), "{}", koopa);
+        assert!(!koopa.contains("@t"), "{}", koopa);
+        assert!(koopa.contains("add 2, %"), "{}", koopa);
+        assert!(!koopa.contains("%end("), "{}", koopa);
+    }
+
+    #[test]
+    fn existing_params() {
+        // new params come after the ones already there, @x is undef if
+        // %then is skipped
+        let koopa = run(r"
+fun @f(@c: i32): i32 {
+%entry:
+  @x = alloc i32
+  br @c, %then, %join(1)
+%then:
+  store 5, @x
+  jump %join(2)
+%join(%p: i32):
+  %1 = load @x
+  %2 = add %p, %1
+  ret %2
+}
+");
+        assert!(!koopa.contains("alloc"), "{}", koopa);
+        assert!(koopa.contains("%join(%p: i32, %x: i32)"), "{}", koopa);
+        assert!(koopa.contains("jump %join(2, 5)"), "{}", koopa);
+        assert!(koopa.contains("%join(1, undef)"), "{}", koopa);
+    }
+}
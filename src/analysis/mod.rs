@@ -1,10 +1,81 @@
-use koopa::ir::Value;
+// no pass eliminates common subexpressions yet
+#[cfg(test)]
+pub mod available_exprs;
+pub mod cfg;
+pub mod constfold;
+pub mod dataflow;
+pub mod dce;
+pub mod dominators;
+pub mod live_variable;
+// no pass is loop aware yet
+#[cfg(test)]
+pub mod loops;
+pub mod manager;
+pub mod mem2reg;
+pub mod pass;
+pub mod reaching_defs;
+pub mod rewrite;
+pub mod uninit;
 
-pub struct value_life {
-    val: Value
-    
-}
+#[cfg(test)]
+pub(crate) mod test_util {
+    use koopa::front::Driver;
+    use koopa::ir::{BasicBlock, FunctionData, Program, Value};
+
+    /// an if-else in a loop, the branches join before going back
+    pub const DIAMOND_IN_LOOP: &str = r"
+fun @f(@n: i32): i32 {
+%entry:
+  jump %cond
+%cond:
+  br @n, %then, %end
+%then:
+  br @n, %left, %right
+%left:
+  jump %join
+%right:
+  jump %join
+%join:
+  jump %cond
+%end:
+  ret 0
+}";
+
+    pub fn parse(koopa: &str) -> Program {
+        Driver::from(koopa).generate_program().unwrap()
+    }
+
+    /// the function with a body, tests declare the others
+    pub fn only_func(program: &Program) -> &FunctionData {
+        let funcs = program.func_layout().iter().map(|&func| program.func(func));
+        let mut funcs = funcs.filter(|func| func.layout().entry_bb().is_some());
+        let func = funcs.next().unwrap();
+        assert!(funcs.next().is_none());
+        func
+    }
 
-fn live_variable() {
+    /// the block called `%name`
+    pub fn bb(func: &FunctionData, name: &str) -> BasicBlock {
+        let name = format!("%{}", name);
+        *func
+            .dfg()
+            .bbs()
+            .iter()
+            .find(|(_, data)| data.name().as_deref() == Some(name.as_str()))
+            .unwrap()
+            .0
+    }
 
+    /// the value called `name`, a param or an instruction
+    pub fn value(func: &FunctionData, name: &str) -> Value {
+        let params = func.params().iter();
+        let bb_params = func.dfg().bbs().values().flat_map(|bb| bb.params());
+        let insts = func.layout().bbs().nodes().flat_map(|node| node.insts().keys());
+        params
+            .chain(bb_params)
+            .chain(insts)
+            .copied()
+            .find(|&v| func.dfg().value(v).name().as_deref() == Some(name))
+            .unwrap()
+    }
 }
@@ -0,0 +1,170 @@
+use std::fmt;
+
+use koopa::ir::{FunctionData, Program};
+
+use super::constfold::ConstFold;
+use super::dce::Dce;
+use super::manager::{AnalysisManager, FunctionAnalyses};
+use super::mem2reg::Mem2Reg;
+
+/// a pass over the whole program
+pub trait ModulePass {
+    fn name(&self) -> &'static str;
+    fn run(&mut self, program: &mut Program, analyses: &mut AnalysisManager);
+}
+
+/// a pass over one function with a body at a time
+pub trait FunctionPass {
+    fn name(&self) -> &'static str;
+    fn run_on_function(&mut self, func: &mut FunctionData, analyses: &mut FunctionAnalyses);
+
+    /// whether the pass leaves blocks and edges alone, so the cached
+    /// analyses of the function still hold
+    fn preserves_cfg(&self) -> bool {
+        false
+    }
+}
+
+/// runs a function pass on every function with a body
+struct FunctionPassAdaptor<P>(P);
+
+impl<P: FunctionPass> ModulePass for FunctionPassAdaptor<P> {
+    fn name(&self) -> &'static str {
+        self.0.name()
+    }
+
+    fn run(&mut self, program: &mut Program, analyses: &mut AnalysisManager) {
+        for func in program.func_layout().to_vec() {
+            let analyses = analyses.function(func);
+            let func = program.func_mut(func);
+            if func.layout().entry_bb().is_some() {
+                self.0.run_on_function(func, analyses);
+                if !self.0.preserves_cfg() {
+                    analyses.invalidate();
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    UnknownPass(String),
+    UnknownLevel(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::UnknownPass(name) => {
+                let names: Vec<_> = all_passes().iter().map(|pass| pass.name()).collect();
+                write!(f, "unknown pass: {}, expected one of {}", name, names.join(", "))
+            }
+            Error::UnknownLevel(level) => write!(f, "unknown optimization level: -O{}", level),
+        }
+    }
+}
+
+/// every pass `--passes=` can name
+fn all_passes() -> Vec<Box<dyn ModulePass>> {
+    vec![
+        Box::new(FunctionPassAdaptor(Mem2Reg)),
+        Box::new(FunctionPassAdaptor(ConstFold)),
+        Box::new(FunctionPassAdaptor(Dce)),
+    ]
+}
+
+/// runs passes over koopa IR in order
+#[derive(Default)]
+pub struct PassManager {
+    passes: Vec<Box<dyn ModulePass>>,
+}
+
+impl PassManager {
+    /// the passes called `names`, in that order
+    pub fn with_names(names: &[&str]) -> Result<Self, Error> {
+        let mut pm = PassManager::default();
+        for &name in names {
+            let pass = all_passes()
+                .into_iter()
+                .find(|pass| pass.name() == name)
+                .ok_or_else(|| Error::UnknownPass(name.to_string()))?;
+            pm.passes.push(pass);
+        }
+        Ok(pm)
+    }
+
+    /// the pipeline of `-O<level>`
+    pub fn with_level(level: u8) -> Self {
+        let names: &[&str] = match level {
+            0 => &[],
+            1 => &["mem2reg", "dce"],
+            _ => &["mem2reg", "constfold", "dce"],
+        };
+        PassManager::with_names(names).unwrap()
+    }
+
+    pub fn run(&mut self, program: &mut Program) {
+        let mut analyses = AnalysisManager::default();
+        for pass in &mut self.passes {
+            pass.run(program, &mut analyses);
+        }
+    }
+}
+
+/// optimizations picked on the command line
+pub struct Options {
+    pub level: u8,
+    // replaces the pipeline of the level
+    pub passes: Option<Vec<String>>,
+    // the peephole optimizer of the backend
+    pub peephole: bool,
+    // annotates the -koopa output with live values
+    pub dump_live: bool,
+}
+
+impl Options {
+    /// picks -O0, -O1, -O2, --passes=a,b,c, -p and --dump-live out of the
+    /// command line, later ones win, the peephole optimizer runs from -O1 on
+    pub fn from_args(args: &[String]) -> Result<Self, Error> {
+        let mut level = 0;
+        let mut passes = None;
+        let mut peephole = false;
+        let mut dump_live = false;
+        for arg in args {
+            if let Some(l) = arg.strip_prefix("-O") {
+                level = match l {
+                    "0" => 0,
+                    "1" => 1,
+                    "2" => 2,
+                    _ => return Err(Error::UnknownLevel(l.to_string())),
+                };
+            } else if let Some(names) = arg.strip_prefix("--passes=") {
+                let names: Vec<_> = names.split(',').filter(|name| !name.is_empty()).collect();
+                // fail before compiling anything
+                PassManager::with_names(&names)?;
+                passes = Some(names.into_iter().map(String::from).collect());
+            } else if arg == "-p" {
+                peephole = true;
+            } else if arg == "--dump-live" {
+                dump_live = true;
+            }
+        }
+        Ok(Options {
+            level,
+            passes,
+            peephole: peephole || level >= 1,
+            dump_live,
+        })
+    }
+
+    pub fn pass_manager(&self) -> PassManager {
+        match &self.passes {
+            Some(names) => {
+                let names: Vec<_> = names.iter().map(String::as_str).collect();
+                PassManager::with_names(&names).unwrap()
+            }
+            None => PassManager::with_level(self.level),
+        }
+    }
+}
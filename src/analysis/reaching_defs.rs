@@ -0,0 +1,179 @@
+use std::collections::{HashMap, HashSet};
+
+use koopa::ir::{FunctionData, Value, ValueKind};
+
+use super::cfg::Cfg;
+use super::dataflow::{solve, Analysis, Direction};
+use super::mem2reg::is_promotable;
+
+/// which stores to an `alloc` slot may reach each load of it, for the slots
+/// whose address never escapes, the alloc itself is a def which stands for
+/// the slot before any store
+pub struct ReachingDefs {
+    // defs of each load, in layout order
+    use_def: HashMap<Value, Vec<Value>>,
+    slots: HashSet<Value>,
+}
+
+impl ReachingDefs {
+    pub fn new(func: &FunctionData, cfg: &Cfg) -> Self {
+        let insts: Vec<_> = func
+            .layout()
+            .bbs()
+            .nodes()
+            .flat_map(|node| node.insts().keys())
+            .copied()
+            .collect();
+        let slots: HashSet<_> = insts
+            .iter()
+            .copied()
+            .filter(|&inst| is_promotable(func, inst))
+            .collect();
+        let mut defs = HashMap::new();
+        for &inst in &insts {
+            match func.dfg().value(inst).kind() {
+                ValueKind::Alloc(_) if slots.contains(&inst) => defs.insert(inst, inst),
+                ValueKind::Store(store) if slots.contains(&store.dest()) => {
+                    defs.insert(inst, store.dest())
+                }
+                _ => continue,
+            };
+        }
+        let solution = solve(&Reaching { defs: &defs }, func, cfg);
+
+        let position: HashMap<_, _> = insts.iter().enumerate().map(|(i, &v)| (v, i)).collect();
+        let mut use_def = HashMap::new();
+        for &inst in &insts {
+            let src = match func.dfg().value(inst).kind() {
+                ValueKind::Load(load) if slots.contains(&load.src()) => load.src(),
+                _ => continue,
+            };
+            let mut reaching: Vec<_> = solution
+                .before(inst)
+                .iter()
+                .copied()
+                .filter(|def| defs[def] == src)
+                .collect();
+            reaching.sort_by_key(|def| position[def]);
+            use_def.insert(inst, reaching);
+        }
+        ReachingDefs { use_def, slots }
+    }
+
+    /// the stores whose value `load` may read, and the alloc if it may read
+    /// the slot before any store
+    pub fn defs(&self, load: Value) -> &[Value] {
+        self.use_def.get(&load).map_or(&[], Vec::as_slice)
+    }
+
+    /// the loads of `func` that may read what `def` wrote, in layout order
+    #[cfg(test)]
+    pub fn uses(&self, func: &FunctionData, def: Value) -> Vec<Value> {
+        let insts = func.layout().bbs().nodes().flat_map(|node| node.insts().keys());
+        insts
+            .copied()
+            .filter(|&inst| self.defs(inst).contains(&def))
+            .collect()
+    }
+
+    /// whether `load` may read its slot before anything is stored to it
+    pub fn may_be_uninit(&self, load: Value) -> bool {
+        self.defs(load).iter().any(|def| self.slots.contains(def))
+    }
+}
+
+/// a def kills the other defs of its slot
+struct Reaching<'a> {
+    // slot of each def
+    defs: &'a HashMap<Value, Value>,
+}
+
+impl Analysis for Reaching<'_> {
+    type Fact = HashSet<Value>;
+
+    const DIRECTION: Direction = Direction::Forward;
+
+    fn boundary(&self, _func: &FunctionData) -> Self::Fact {
+        HashSet::new()
+    }
+
+    fn top(&self, _func: &FunctionData) -> Self::Fact {
+        HashSet::new()
+    }
+
+    fn meet(&self, fact: &mut Self::Fact, other: &Self::Fact) {
+        fact.extend(other);
+    }
+
+    fn transfer(&self, _func: &FunctionData, inst: Value, fact: &mut Self::Fact) {
+        if let Some(&slot) = self.defs.get(&inst) {
+            fact.retain(|def| self.defs[def] != slot);
+            fact.insert(inst);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use koopa::ir::{FunctionData, Value, ValueKind};
+
+    use super::ReachingDefs;
+    use crate::analysis::cfg::Cfg;
+    use crate::analysis::test_util::{only_func, parse, value};
+
+    fn stores(func: &FunctionData) -> Vec<Value> {
+        let insts = func.layout().bbs().nodes().flat_map(|node| node.insts().keys());
+        insts
+            .copied()
+            .filter(|&inst| matches!(func.dfg().value(inst).kind(), ValueKind::Store(_)))
+            .collect()
+    }
+
+    #[test]
+    fn chains() {
+        let program = parse(
+            r"
+fun @f(@n: i32): i32 {
+%entry:
+  @x = alloc i32
+  @y = alloc i32
+  @a = alloc [i32, 2]
+  store 1, @x
+  br @n, %then, %end
+%then:
+  store 2, @x
+  %y0 = load @y
+  jump %end
+%end:
+  %x0 = load @x
+  store %x0, @y
+  store 3, @x
+  %x1 = load @x
+  %y1 = load @y
+  %p = getelemptr @a, 0
+  %a0 = load %p
+  ret %x1
+}",
+        );
+        let func = only_func(&program);
+        let defs = ReachingDefs::new(func, &Cfg::new(func));
+        let [store_x1, store_x2, store_y, store_x3] = stores(func)[..] else {
+            panic!()
+        };
+        let [x0, x1, y0, y1] = ["%x0", "%x1", "%y0", "%y1"].map(|name| value(func, name));
+
+        assert_eq!(defs.defs(x0), [store_x1, store_x2]);
+        assert_eq!(defs.defs(x1), [store_x3]);
+        assert_eq!(defs.defs(y0), [value(func, "@y")]);
+        assert_eq!(defs.defs(y1), [store_y]);
+        assert!(defs.may_be_uninit(y0));
+        assert!(!defs.may_be_uninit(x0));
+        // the array is not a slot
+        assert_eq!(defs.defs(value(func, "%a0")), []);
+
+        assert_eq!(defs.uses(func, store_x1), [x0]);
+        assert_eq!(defs.uses(func, store_x2), [x0]);
+        assert_eq!(defs.uses(func, value(func, "@y")), [y0]);
+        assert_eq!(defs.uses(func, value(func, "@x")), []);
+    }
+}
@@ -0,0 +1,124 @@
+use std::collections::{HashMap, HashSet};
+
+use koopa::ir::builder_traits::{BasicBlockBuilder, ValueBuilder};
+use koopa::ir::dfg::DataFlowGraph;
+use koopa::ir::entities::ValueData;
+use koopa::ir::{Type, Value, ValueKind};
+
+/// a block param of type `ty` which is not in any block yet, `index` is its
+/// position in the params of the block it goes to
+pub fn new_block_param(dfg: &mut DataFlowGraph, ty: Type, index: usize) -> Value {
+    // koopa only makes block params along with their block, so one is taken
+    // from a scratch block
+    let scratch = dfg.new_bb().basic_block_with_params(None, vec![ty]);
+    let param = dfg.bb_mut(scratch).params_mut().pop().unwrap();
+    dfg.remove_bb(scratch);
+    set_param_index(dfg, param, index);
+    param
+}
+
+pub fn set_param_index(dfg: &mut DataFlowGraph, param: Value, index: usize) {
+    let mut data = dfg.value(param).clone();
+    match data.kind_mut() {
+        ValueKind::BlockArgRef(arg) if arg.index() != index => *arg.index_mut() = index,
+        _ => return,
+    }
+    replace_data(dfg, param, data);
+}
+
+/// every use of a key of `replaced` becomes a use of its value, values may
+/// be keys again
+pub fn replace_uses(dfg: &mut DataFlowGraph, replaced: &HashMap<Value, Value>) {
+    let resolve = |mut value: Value| {
+        while let Some(&v) = replaced.get(&value) {
+            value = v;
+        }
+        value
+    };
+    // the users of a key become users of what replaces it, so the order has
+    // to put them after it too
+    let mut new_users: HashMap<Value, Vec<Value>> = HashMap::new();
+    for &old in replaced.keys() {
+        let users = dfg.value(old).used_by().iter().copied();
+        new_users.entry(resolve(old)).or_default().extend(users);
+    }
+    let mut order = vec![];
+    let mut visited = HashSet::new();
+    for users in new_users.values() {
+        for &user in users {
+            post_order_users(dfg, user, &new_users, &mut visited, &mut order);
+        }
+    }
+    for &value in order.iter().rev() {
+        let mut data = dfg.value(value).clone();
+        for_each_operand(&mut data, |v| *v = resolve(*v));
+        dfg.replace_value_with(value).raw(data);
+    }
+}
+
+/// koopa forgets the users of a value whose data is replaced, so they are
+/// replaced again after it, each one after the values it uses
+pub fn replace_data(dfg: &mut DataFlowGraph, value: Value, data: ValueData) {
+    let mut order = vec![];
+    post_order_users(dfg, value, &HashMap::new(), &mut HashSet::new(), &mut order);
+    dfg.replace_value_with(value).raw(data);
+    for &user in order.iter().rev().skip(1) {
+        let data = dfg.value(user).clone();
+        dfg.replace_value_with(user).raw(data);
+    }
+}
+
+// data flow has no cycles, block params are the only values defined by a
+// loop and they have no operands, so the reverse is a topological order
+fn post_order_users(
+    dfg: &DataFlowGraph,
+    value: Value,
+    new_users: &HashMap<Value, Vec<Value>>,
+    visited: &mut HashSet<Value>,
+    order: &mut Vec<Value>,
+) {
+    if !visited.insert(value) {
+        return;
+    }
+    let users = dfg.value(value).used_by().iter();
+    for &user in users.chain(new_users.get(&value).into_iter().flatten()) {
+        post_order_users(dfg, user, new_users, visited, order);
+    }
+    order.push(value);
+}
+
+/// calls `f` on every value operand of an instruction
+pub fn for_each_operand(data: &mut ValueData, mut f: impl FnMut(&mut Value)) {
+    match data.kind_mut() {
+        ValueKind::Load(load) => f(load.src_mut()),
+        ValueKind::Store(store) => {
+            f(store.value_mut());
+            f(store.dest_mut());
+        }
+        ValueKind::GetPtr(gp) => {
+            f(gp.src_mut());
+            f(gp.index_mut());
+        }
+        ValueKind::GetElemPtr(gep) => {
+            f(gep.src_mut());
+            f(gep.index_mut());
+        }
+        ValueKind::Binary(binary) => {
+            f(binary.lhs_mut());
+            f(binary.rhs_mut());
+        }
+        ValueKind::Branch(br) => {
+            f(br.cond_mut());
+            br.true_args_mut().iter_mut().for_each(&mut f);
+            br.false_args_mut().iter_mut().for_each(&mut f);
+        }
+        ValueKind::Jump(jump) => jump.args_mut().iter_mut().for_each(f),
+        ValueKind::Call(call) => call.args_mut().iter_mut().for_each(f),
+        ValueKind::Return(ret) => {
+            if let Some(value) = ret.value_mut() {
+                f(value);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,42 @@
+use std::collections::{HashMap, HashSet};
+
+use koopa::ir::{Program, ValueKind};
+
+use super::cfg::Cfg;
+use super::reaching_defs::ReachingDefs;
+use crate::ast::Span;
+use crate::ir_gen::Read;
+
+/// reads of locals which may come before anything is assigned to them, with
+/// what to tell about them, a variable is only reported at its first read
+///
+/// `program` must be as generated, before any pass, with -ssa locals are not
+/// in memory and there is nothing to find
+pub fn check(program: &Program, reads: &[Read]) -> Vec<(Span, String)> {
+    let mut reads: Vec<_> = reads.iter().collect();
+    reads.sort_by_key(|read| read.span.start);
+
+    let mut defs = HashMap::new();
+    let mut reported = HashSet::new();
+    let mut found = vec![];
+    for read in reads {
+        let func = program.func(read.func);
+        let defs = defs
+            .entry(read.func)
+            .or_insert_with(|| ReachingDefs::new(func, &Cfg::new(func)));
+        let ValueKind::Load(load) = func.dfg().value(read.load).kind() else {
+            unreachable!("reads are loads")
+        };
+        if !defs.may_be_uninit(read.load) || !reported.insert((read.func, load.src())) {
+            continue;
+        }
+        // only the alloc reaches, nothing is stored on any path
+        let msg = if defs.defs(read.load) == [load.src()] {
+            format!("`{}` is used uninitialized", read.name)
+        } else {
+            format!("`{}` may be used uninitialized", read.name)
+        };
+        found.push((read.span, msg));
+    }
+    found
+}
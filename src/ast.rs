@@ -1,12 +1,42 @@
+use lalrpop_util::{ErrorRecovery, ParseError};
+
+use crate::diagnostic::UserError;
+
+/// byte offsets `[start, end)` into the source file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+}
+
 #[derive(Debug)]
 pub struct CompUnit {
     pub comp_unit: Box<Option<CompUnit>>,
     pub global: Global,
 }
 
+impl CompUnit {
+    /// globals in source order
+    pub fn globals(&self) -> Vec<&Global> {
+        let mut globals = match self.comp_unit.as_ref() {
+            Some(comp_unit) => comp_unit.globals(),
+            None => vec![],
+        };
+        globals.push(&self.global);
+        globals
+    }
+}
+
 #[derive(Debug)]
 pub enum Global {
     FuncDef(FuncDef),
+    FuncDecl(FuncDecl),
     Decl(Decl),
 }
 
@@ -16,12 +46,25 @@ pub struct FuncDef {
     pub ident: String,
     pub params: Vec<FuncFParam>,
     pub block: Block,
+    pub span: Span,
+}
+
+/// int f(int x);
+#[derive(Debug)]
+pub struct FuncDecl {
+    pub func_type: FuncType,
+    pub ident: String,
+    pub params: Vec<FuncFParam>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct FuncFParam {
     pub btype: BType,
     pub ident: String,
+    // int a[][5] => Some([5])
+    pub dims: Option<Vec<ConstExp>>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
@@ -31,11 +74,21 @@ pub enum AllType {
 }
 
 impl AllType {
-    pub(crate) fn to_btype(&self) -> BType {
-        match self {
-            AllType::Int => BType::Int,
-            AllType::Void => panic!("cant use void as BType"),
+    /// `void` is only a return type, a variable or parameter of it is
+    /// reported at `span` and taken as `int`
+    pub(crate) fn to_btype<T>(
+        &self,
+        span: Span,
+        errors: &mut Vec<ErrorRecovery<usize, T, UserError>>,
+    ) -> BType {
+        if let AllType::Void = self {
+            let error = UserError::new(span, "variable or parameter declared void");
+            errors.push(ErrorRecovery {
+                error: ParseError::User { error },
+                dropped_tokens: vec![],
+            });
         }
+        BType::Int
     }
 
     pub(crate) fn to_func_type(&self) -> FuncType {
@@ -55,41 +108,69 @@ pub enum FuncType {
 #[derive(Debug)]
 pub struct Block {
     pub block_item: Vec<BlockItem>,
+    pub span: Span,
 }
 
+// statements are big with their spans, boxing them buys nothing in a tree built once
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug)]
 pub enum BlockItem {
     Decl(Decl),
     Stmt(Stmt),
 }
 
+impl BlockItem {
+    pub fn span(&self) -> Span {
+        match self {
+            BlockItem::Decl(decl) => decl.span(),
+            BlockItem::Stmt(stmt) => stmt.span(),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum Decl {
     ConstDecl(ConstDecl),
     VarDecl(VarDecl),
 }
 
+impl Decl {
+    pub fn span(&self) -> Span {
+        match self {
+            Decl::ConstDecl(decl) => decl.span,
+            Decl::VarDecl(decl) => decl.span,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct VarDecl {
     pub btype: BType,
     pub defs: Vec<VarDef>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
-pub enum VarDef {
-    Id(String),
-    Assign(String, InitVal),
+pub struct VarDef {
+    pub ident: String,
+    pub dims: Vec<ConstExp>,
+    pub init_val: Option<InitVal>,
+    pub span: Span,
 }
 
+// a lone expression outweighs a list, it is the common case though
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug)]
-pub struct InitVal {
-    pub exp: Exp,
+pub enum InitVal {
+    Exp(Exp),
+    List(Vec<InitVal>, Span),
 }
 
 #[derive(Debug)]
 pub struct ConstDecl {
     pub btype: BType,
     pub defs: Vec<ConstDef>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -100,12 +181,17 @@ pub enum BType {
 #[derive(Debug)]
 pub struct ConstDef {
     pub ident: String,
+    pub dims: Vec<ConstExp>,
     pub const_init_val: ConstInitVal,
+    pub span: Span,
 }
 
+// same as `InitVal`
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug)]
 pub enum ConstInitVal {
     ConstExp(ConstExp),
+    List(Vec<ConstInitVal>, Span),
 }
 
 #[derive(Debug)]
@@ -113,22 +199,46 @@ pub enum ConstExp {
     Exp(Exp),
 }
 
+impl ConstExp {
+    pub fn span(&self) -> Span {
+        match self {
+            ConstExp::Exp(exp) => exp.span(),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum Stmt {
-    Assign(LVal, Exp),
-    Exp(Option<Exp>),
+    Assign(LVal, Exp, Span),
+    Exp(Option<Exp>, Span),
     Block(Block),
-    Return(Option<Exp>),
+    Return(Option<Exp>, Span),
     If(Box<If>),
     While(Box<While>),
-    Break,
-    Continue,
+    Break(Span),
+    Continue(Span),
+}
+
+impl Stmt {
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Assign(_, _, span)
+            | Stmt::Exp(_, span)
+            | Stmt::Return(_, span)
+            | Stmt::Break(span)
+            | Stmt::Continue(span) => *span,
+            Stmt::Block(block) => block.span,
+            Stmt::If(if_stmt) => if_stmt.span,
+            Stmt::While(while_stmt) => while_stmt.span,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct While {
     pub cond: Exp,
     pub body: Stmt,
+    pub span: Span,
 }
 
 #[derive(Debug)]
@@ -136,6 +246,7 @@ pub struct If {
     pub cond: Exp,
     pub if_then: Stmt,
     pub else_then: Option<Stmt>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
@@ -143,22 +254,57 @@ pub enum Exp {
     LOrExp(LOrExp),
 }
 
+impl Exp {
+    pub fn span(&self) -> Span {
+        match self {
+            Exp::LOrExp(exp) => exp.span(),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum LOrExp {
     LAndExp(LAndExp),
-    LOrExp(Box<LOrExp>, LAndExp),
+    LOrExp(Box<LOrExp>, LAndExp, Span),
+}
+
+impl LOrExp {
+    pub fn span(&self) -> Span {
+        match self {
+            LOrExp::LAndExp(exp) => exp.span(),
+            LOrExp::LOrExp(_, _, span) => *span,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum LAndExp {
     EqExp(EqExp),
-    LAndExp(Box<LAndExp>, EqExp),
+    LAndExp(Box<LAndExp>, EqExp, Span),
+}
+
+impl LAndExp {
+    pub fn span(&self) -> Span {
+        match self {
+            LAndExp::EqExp(exp) => exp.span(),
+            LAndExp::LAndExp(_, _, span) => *span,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum EqExp {
     RelExp(RelExp),
-    EqExp(Box<EqExp>, EqOp, RelExp),
+    EqExp(Box<EqExp>, EqOp, RelExp, Span),
+}
+
+impl EqExp {
+    pub fn span(&self) -> Span {
+        match self {
+            EqExp::RelExp(exp) => exp.span(),
+            EqExp::EqExp(_, _, _, span) => *span,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -170,7 +316,16 @@ pub enum EqOp {
 #[derive(Debug)]
 pub enum RelExp {
     AddExp(AddExp),
-    RelExp(Box<RelExp>, RelOp, AddExp),
+    RelExp(Box<RelExp>, RelOp, AddExp, Span),
+}
+
+impl RelExp {
+    pub fn span(&self) -> Span {
+        match self {
+            RelExp::AddExp(exp) => exp.span(),
+            RelExp::RelExp(_, _, _, span) => *span,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -184,7 +339,16 @@ pub enum RelOp {
 #[derive(Debug)]
 pub enum AddExp {
     MulExp(MulExp),
-    AddAndMul(Box<AddExp>, AddOp, MulExp),
+    AddAndMul(Box<AddExp>, AddOp, MulExp, Span),
+}
+
+impl AddExp {
+    pub fn span(&self) -> Span {
+        match self {
+            AddExp::MulExp(exp) => exp.span(),
+            AddExp::AddAndMul(_, _, _, span) => *span,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -196,7 +360,16 @@ pub enum AddOp {
 #[derive(Debug)]
 pub enum MulExp {
     UnaryExp(UnaryExp),
-    MulAndUnary(Box<MulExp>, MulOp, UnaryExp),
+    MulAndUnary(Box<MulExp>, MulOp, UnaryExp, Span),
+}
+
+impl MulExp {
+    pub fn span(&self) -> Span {
+        match self {
+            MulExp::UnaryExp(exp) => exp.span(),
+            MulExp::MulAndUnary(_, _, _, span) => *span,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -209,26 +382,50 @@ pub enum MulOp {
 #[derive(Debug)]
 pub enum UnaryExp {
     PrimaryExp(PrimaryExp),
-    UnaryOp(UnaryOp, Box<UnaryExp>),
+    UnaryOp(UnaryOp, Box<UnaryExp>, Span),
     Call(FuncCall),
 }
 
+impl UnaryExp {
+    pub fn span(&self) -> Span {
+        match self {
+            UnaryExp::PrimaryExp(exp) => exp.span(),
+            UnaryExp::UnaryOp(_, _, span) => *span,
+            UnaryExp::Call(call) => call.span,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct FuncCall {
     pub ident: String,
     pub args: Vec<Exp>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub enum PrimaryExp {
     Expression(Box<Exp>),
     LVal(LVal),
-    Number(i32),
+    // out of range but for -2147483648 after the semantic pass
+    Number(i64, Span),
+}
+
+impl PrimaryExp {
+    pub fn span(&self) -> Span {
+        match self {
+            PrimaryExp::Expression(exp) => exp.span(),
+            PrimaryExp::LVal(lval) => lval.span,
+            PrimaryExp::Number(_, span) => *span,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct LVal {
     pub ident: String,
+    pub indices: Vec<Exp>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
@@ -0,0 +1,262 @@
+use std::fmt::{self, Display};
+
+use lalrpop_util::ParseError;
+
+use crate::ast::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Error,
+    Warning,
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Level::Error => write!(f, "error"),
+            Level::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub level: Level,
+    pub span: Span,
+    pub msg: String,
+}
+
+/// gathers the errors and warnings of all stages, so they are reported together
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    items: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn error(&mut self, span: Span, msg: impl Display) {
+        self.push(Level::Error, span, msg);
+    }
+
+    pub fn warning(&mut self, span: Span, msg: impl Display) {
+        self.push(Level::Warning, span, msg);
+    }
+
+    fn push(&mut self, level: Level, span: Span, msg: impl Display) {
+        self.items.push(Diagnostic {
+            level,
+            span,
+            msg: msg.to_string(),
+        });
+    }
+
+    pub fn count(&self, level: Level) -> usize {
+        self.items.iter().filter(|d| d.level == level).count()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.count(Level::Error) > 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// all diagnostics sorted by location, then a summary line
+    pub fn render(&self, source: &Source) -> String {
+        let mut items: Vec<_> = self.items.iter().collect();
+        items.sort_by_key(|d| d.span.start);
+
+        let mut out = String::new();
+        for d in items {
+            out += &source.render(&d.level.to_string(), d.span, &d.msg);
+            out += "\n";
+        }
+        let plural = |n: usize, word: &str| match n {
+            1 => format!("1 {}", word),
+            n => format!("{} {}s", n, word),
+        };
+        let summary = match (self.count(Level::Error), self.count(Level::Warning)) {
+            (0, warnings) => plural(warnings, "warning"),
+            (errors, 0) => plural(errors, "error"),
+            (errors, warnings) => {
+                format!("{} and {}", plural(errors, "error"), plural(warnings, "warning"))
+            }
+        };
+        out + &summary + " generated."
+    }
+}
+
+/// an error raised by a grammar action
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserError {
+    pub span: Span,
+    pub msg: &'static str,
+}
+
+impl UserError {
+    pub fn new(span: Span, msg: &'static str) -> Self {
+        UserError { span, msg }
+    }
+}
+
+impl Display for UserError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.msg)
+    }
+}
+
+/// a source file, used to turn spans into readable locations
+pub struct Source<'a> {
+    pub name: &'a str,
+    pub text: &'a str,
+}
+
+impl<'a> Source<'a> {
+    pub fn new(name: &'a str, text: &'a str) -> Self {
+        Source { name, text }
+    }
+
+    /// 1-based line and column (in chars) of a byte offset
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        let before = &self.text[..offset.min(self.text.len())];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        (line, before[line_start..].chars().count() + 1)
+    }
+
+    /// the line containing `offset`, without the line break
+    fn line_at(&self, offset: usize) -> (usize, &'a str) {
+        let offset = offset.min(self.text.len());
+        let start = self.text[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let end = self.text[offset..]
+            .find('\n')
+            .map_or(self.text.len(), |i| offset + i);
+        (start, self.text[start..end].trim_end_matches('\r'))
+    }
+
+    /// file:line:col: error: msg
+    ///     3 |   a = 1;
+    ///       |   ^
+    ///
+    /// spans over multiple lines are underlined to the end of the first one
+    pub fn render(&self, level: &str, span: Span, msg: &str) -> String {
+        let start = span.start.min(self.text.len());
+        let (line, col) = self.line_col(start);
+        let (line_start, text) = self.line_at(start);
+        let start = start.min(line_start + text.len());
+
+        // keep tabs so the caret lines up with the source line
+        let padding: String = text[..start - line_start]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let underline_end = span.end.clamp(start, line_start + text.len());
+        let width = text[start - line_start..underline_end - line_start]
+            .chars()
+            .count()
+            .max(1);
+
+        let gutter = " ".repeat(line.to_string().len());
+        format!(
+            "{}:{}:{}: {}: {}\n {} | {}\n {} | {}{}",
+            self.name,
+            line,
+            col,
+            level,
+            msg,
+            line,
+            text,
+            gutter,
+            padding,
+            "^".repeat(width)
+        )
+    }
+
+    /// roughly the number of SysY tokens in `[start, end)`, comments are skipped
+    pub fn count_tokens(&self, start: usize, end: usize) -> usize {
+        let text = &self.text[start.min(end)..end.min(self.text.len())];
+        let mut chars = text.chars().peekable();
+        let mut count = 0;
+        while let Some(c) = chars.next() {
+            match c {
+                c if c.is_whitespace() => continue,
+                '/' if chars.peek() == Some(&'/') => {
+                    chars.by_ref().find(|&c| c == '\n');
+                    continue;
+                }
+                '/' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    let mut last = ' ';
+                    for c in chars.by_ref() {
+                        if last == '*' && c == '/' {
+                            break;
+                        }
+                        last = c;
+                    }
+                    continue;
+                }
+                c if c == '_' || c.is_ascii_alphanumeric() => {
+                    while chars.next_if(|&c| c == '_' || c.is_ascii_alphanumeric()).is_some() {}
+                }
+                '<' | '>' | '=' | '!' => {
+                    chars.next_if_eq(&'=');
+                }
+                '&' | '|' => {
+                    chars.next_if_eq(&c);
+                }
+                _ => {}
+            }
+            count += 1;
+        }
+        count
+    }
+
+    /// turns a lalrpop error into the span and message of a diagnostic
+    pub fn parse_error<T: Display>(&self, err: &ParseError<usize, T, UserError>) -> (Span, String) {
+        match err {
+            ParseError::InvalidToken { location } => {
+                let c = self.text[*location..].chars().next().unwrap_or(' ');
+                (
+                    Span::new(*location, *location + c.len_utf8()),
+                    format!("invalid token `{}`", c),
+                )
+            }
+            ParseError::UnrecognizedEof { location, expected } => (
+                Span::new(*location, *location),
+                format!("unexpected end of file, {}", expected_tokens(expected)),
+            ),
+            ParseError::UnrecognizedToken {
+                token: (l, token, r),
+                expected,
+            } => (
+                Span::new(*l, *r),
+                format!("unexpected `{}`, {}", token, expected_tokens(expected)),
+            ),
+            ParseError::ExtraToken { token: (l, token, r) } => (
+                Span::new(*l, *r),
+                format!("extra `{}` after the end of the program", token),
+            ),
+            ParseError::User { error } => (error.span, error.to_string()),
+        }
+    }
+}
+
+/// lalrpop names terminals after the grammar, "\"int\"" or the regex r#"..."#
+fn expected_tokens(expected: &[String]) -> String {
+    let mut names: Vec<&str> = vec![];
+    for token in expected {
+        let name = match token.as_str() {
+            r##"r#"[_a-zA-Z][_a-zA-Z0-9]*"#"## => "identifier",
+            t if t.starts_with("r#") => "integer literal",
+            t => t,
+        };
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    match names.as_slice() {
+        [] => "expected nothing".to_string(),
+        [name] => format!("expected {}", name),
+        _ => format!("expected one of {}", names.join(", ")),
+    }
+}
@@ -0,0 +1,297 @@
+use std::collections::{HashMap, HashSet};
+
+use koopa::ir::builder::{GlobalBuilder, LocalBuilder};
+use koopa::ir::builder_traits::{BasicBlockBuilder, LocalInstBuilder, ValueBuilder};
+use koopa::ir::{BasicBlock, Function, FunctionData, Program, Type, Value, ValueKind};
+
+use super::ssa::Ssa;
+use super::Read;
+use crate::ast::Span;
+
+/// builds the koopa program, instructions are appended to the insertion
+/// block of the function being generated
+pub struct IrBuilder {
+    program: Program,
+    func: Option<Function>,
+    insert_point: Option<BasicBlock>,
+    // names of the functions and global variables
+    global_names: HashSet<String>,
+    // names taken in the current function, including the globals
+    names: HashSet<String>,
+    // last suffix given to each name
+    name_counters: HashMap<String, usize>,
+    // builds SSA form for scalar locals, for each function
+    ssa_enabled: bool,
+    ssa: Option<Ssa>,
+    reads: Vec<Read>,
+}
+
+impl IrBuilder {
+    pub fn new(ssa: bool) -> Self {
+        IrBuilder {
+            program: Program::new(),
+            func: None,
+            insert_point: None,
+            global_names: HashSet::new(),
+            names: HashSet::new(),
+            name_counters: HashMap::new(),
+            ssa_enabled: ssa,
+            ssa: None,
+            reads: vec![],
+        }
+    }
+
+    pub fn into_program(self) -> (Program, Vec<Read>) {
+        (self.program, self.reads)
+    }
+
+    /// adds a function, or the declaration of an external one
+    pub fn new_func(&mut self, data: FunctionData) -> Function {
+        self.global_names.insert(data.name().to_string());
+        self.program.new_func(data)
+    }
+
+    /// values outside of any function, global allocs and their initializers
+    pub fn global_value(&mut self) -> GlobalBuilder<'_> {
+        self.program.new_value()
+    }
+
+    pub fn set_global_name(&mut self, value: Value, name: String) {
+        self.global_names.insert(name.clone());
+        self.program.set_value_name(value, Some(name));
+    }
+
+    /// starts generating the body of `func` in its entry block, the names of
+    /// its locals start over
+    pub fn enter_function(&mut self, func: Function) {
+        self.func = Some(func);
+        self.names = self.global_names.clone();
+        self.name_counters.clear();
+        if self.ssa_enabled {
+            self.ssa = Some(Ssa::default());
+        }
+        let entry = self.new_bb("%entry");
+        self.set_insert_point(entry);
+    }
+
+    pub fn exit_function(&mut self) {
+        let func = self.func();
+        assert_eq!(
+            func.dfg().bbs().len(),
+            func.layout().bbs().len(),
+            "a block of {} is never generated",
+            func.name()
+        );
+        if let Some(mut ssa) = self.ssa.take() {
+            ssa.finish(self.func_mut());
+        }
+        self.func = None;
+        self.insert_point = None;
+    }
+
+    /// the function being generated
+    pub fn func(&self) -> &FunctionData {
+        self.program.func(self.func.unwrap())
+    }
+
+    fn func_mut(&mut self) -> &mut FunctionData {
+        self.program.func_mut(self.func.unwrap())
+    }
+
+    /// a value of the function which is not in any block, such as a constant,
+    /// instructions built by it still have to be `insert`ed
+    pub fn new_value(&mut self) -> LocalBuilder<'_> {
+        self.func_mut().dfg_mut().new_value()
+    }
+
+    pub fn integer(&mut self, value: i32) -> Value {
+        self.new_value().integer(value)
+    }
+
+    /// the value of an `integer` of the current function
+    pub fn integer_value(&self, value: Value) -> Option<i32> {
+        match self.program.func(self.func?).dfg().value(value).kind() {
+            ValueKind::Integer(int) => Some(int.value()),
+            _ => None,
+        }
+    }
+
+    pub fn value_type(&self, value: Value) -> Type {
+        if value.is_global() {
+            self.program.borrow_value(value).ty().clone()
+        } else {
+            self.func().dfg().value(value).ty().clone()
+        }
+    }
+
+    /// a new block of the function, `name` is made unique
+    pub fn new_bb(&mut self, name: &str) -> BasicBlock {
+        let name = self.unique_name(name);
+        self.func_mut().dfg_mut().new_bb().basic_block(Some(name))
+    }
+
+    /// blocks are laid out when they are first inserted into, so that they
+    /// follow the order of the code rather than the order they are created in,
+    /// all jumps to `bb` must be generated by then
+    pub fn set_insert_point(&mut self, bb: BasicBlock) {
+        self.set_insert_point_unsealed(bb);
+        self.seal(bb);
+    }
+
+    /// like `set_insert_point`, for a block which more jumps lead to later,
+    /// such as a loop header, it has to be `seal`ed after the last of them
+    pub fn set_insert_point_unsealed(&mut self, bb: BasicBlock) {
+        let bbs = self.func_mut().layout_mut().bbs_mut();
+        if !bbs.contains_key(&bb) {
+            bbs.extend([bb]);
+        }
+        self.insert_point = Some(bb);
+    }
+
+    /// all jumps to `bb` are generated
+    pub fn seal(&mut self, bb: BasicBlock) {
+        let func = self.program.func_mut(self.func.unwrap());
+        if let Some(ssa) = &mut self.ssa {
+            ssa.seal(func, bb);
+            self.name_params();
+        }
+    }
+
+    /// memory for a local named `name`, with SSA form an `i32` is a variable
+    /// instead, which is never allocated
+    pub fn alloc(&mut self, ty: Type, name: &str) -> Value {
+        let is_var = self.ssa.is_some() && ty.is_i32();
+        let alloc = self.new_value().alloc(ty);
+        self.set_name(alloc, name);
+        match &mut self.ssa {
+            Some(ssa) if is_var => ssa.add_var(alloc),
+            _ => {
+                self.insert(alloc);
+            }
+        }
+        alloc
+    }
+
+    /// loads from `ptr`, or reads the variable
+    pub fn load(&mut self, ptr: Value) -> Value {
+        let func = self.program.func_mut(self.func.unwrap());
+        match &mut self.ssa {
+            Some(ssa) if ssa.is_var(ptr) => {
+                let value = ssa.read(func, ptr, self.insert_point.unwrap());
+                self.name_params();
+                value
+            }
+            _ => self.build(|b| b.load(ptr)),
+        }
+    }
+
+    /// loads the variable `name` read at `span`, remembering where the load
+    /// comes from unless it is an SSA variable
+    pub fn read(&mut self, ptr: Value, name: &str, span: Span) -> Value {
+        let value = self.load(ptr);
+        if let ValueKind::Load(_) = self.func().dfg().value(value).kind() {
+            self.reads.push(Read {
+                func: self.func.unwrap(),
+                load: value,
+                name: name.to_string(),
+                span,
+            });
+        }
+        value
+    }
+
+    /// stores to `ptr`, or defines the variable
+    pub fn store(&mut self, value: Value, ptr: Value) {
+        match &mut self.ssa {
+            Some(ssa) if ssa.is_var(ptr) => ssa.write(ptr, self.insert_point.unwrap(), value),
+            _ => {
+                self.build(|b| b.store(value, ptr));
+            }
+        }
+    }
+
+    /// block params of a variable `@x` or `%x` are named `%x`
+    fn name_params(&mut self) {
+        let new_params = self.ssa.as_mut().unwrap().take_new_params();
+        for (param, var) in new_params {
+            let dfg = self.func().dfg();
+            if !dfg.values().contains_key(&param) {
+                continue;
+            }
+            let name = dfg.value(var).name().as_ref().unwrap()[1..].to_string();
+            self.set_name(param, &format!("%{}", name));
+        }
+    }
+
+    /// whether the insertion block already ends with `ret`, `jump` or `br`,
+    /// nothing can be added to it after that
+    pub fn is_terminated(&self) -> bool {
+        let func = self.func();
+        let last_inst = func
+            .layout()
+            .bbs()
+            .node(&self.insert_point.unwrap())
+            .and_then(|node| node.insts().back_key());
+        match last_inst {
+            Some(&inst) => matches!(
+                func.dfg().value(inst).kind(),
+                ValueKind::Return(_) | ValueKind::Jump(_) | ValueKind::Branch(_)
+            ),
+            None => false,
+        }
+    }
+
+    /// appends `inst` to the insertion block
+    pub fn insert(&mut self, inst: Value) -> Value {
+        assert!(
+            !self.is_terminated(),
+            "instruction after the terminator of a block"
+        );
+        let bb = self.insert_point.unwrap();
+        self.func_mut().layout_mut().bb_mut(bb).insts_mut().extend([inst]);
+        if let Some(ssa) = &mut self.ssa {
+            let func = self.program.func(self.func.unwrap());
+            match func.dfg().value(inst).kind() {
+                ValueKind::Jump(jump) => ssa.add_pred(jump.target(), bb),
+                ValueKind::Branch(br) => {
+                    ssa.add_pred(br.true_bb(), bb);
+                    ssa.add_pred(br.false_bb(), bb);
+                }
+                _ => {}
+            }
+        }
+        inst
+    }
+
+    /// builds an instruction and appends it to the insertion block
+    pub fn build(&mut self, build: impl FnOnce(LocalBuilder) -> Value) -> Value {
+        let inst = build(self.new_value());
+        self.insert(inst)
+    }
+
+    /// falls through to `target` unless the insertion block is already terminated
+    pub fn fall_through(&mut self, target: BasicBlock) {
+        if !self.is_terminated() {
+            self.build(|b| b.jump(target));
+        }
+    }
+
+    /// names a local value, `@x` or `%x`, unique in the function
+    pub fn set_name(&mut self, value: Value, name: &str) {
+        let name = self.unique_name(name);
+        self.func_mut().dfg_mut().set_value_name(value, Some(name));
+    }
+
+    /// `name` if it's not taken in the function yet, otherwise `name_1`,
+    /// `name_2`... so that koopa symbols are unique
+    fn unique_name(&mut self, name: &str) -> String {
+        let mut unique = name.to_string();
+        while self.names.contains(&unique) {
+            let n = self.name_counters.entry(name.to_string()).or_insert(0);
+            *n += 1;
+            unique = format!("{}_{}", name, n);
+        }
+        self.names.insert(unique.clone());
+        unique
+    }
+}
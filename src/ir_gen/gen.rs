@@ -1,120 +1,45 @@
 use crate::ast::*;
-use crate::ir_gen::scope::Scope;
-use crate::ir_gen::Error::Undefined;
+use crate::ir_gen::builder::IrBuilder;
+use crate::ir_gen::init::{flatten, Initializer};
+use crate::ir_gen::scope::{ConstArray, Scope};
+use std::collections::HashSet;
 use koopa::ir::builder::GlobalInstBuilder;
-use koopa::ir::builder_traits::{BasicBlockBuilder, LocalInstBuilder, ValueBuilder};
-use koopa::ir::{BasicBlock, BinaryOp, FunctionData, Program, Type, Value, ValueKind};
+use koopa::ir::builder_traits::{LocalInstBuilder, ValueBuilder};
+use koopa::ir::{BasicBlock, BinaryOp, FunctionData, Type, TypeKind, Value};
 
-use super::eval::Evaluate;
-use super::{Error, Result};
-
-macro_rules! curr_func_mut {
-    ($program:expr, $scope:expr) => {
-        $program.func_mut($scope.function.unwrap())
-    };
-}
-
-macro_rules! new_value {
-    ($program:expr, $scope:expr) => {
-        $program
-            .func_mut($scope.function.unwrap())
-            .dfg_mut()
-            .new_value()
-    };
-}
-
-macro_rules! new_bb {
-    ($program:expr, $scope:expr) => {
-        $program
-            .func_mut($scope.function.unwrap())
-            .dfg_mut()
-            .new_bb()
-    };
-}
-
-macro_rules! add_bb_to_program {
-    ($program:expr, $scope:expr, $bb: expr) => {
-        curr_func_mut!($program, $scope)
-            .layout_mut()
-            .bbs_mut()
-            .extend([$bb]);
-    };
-}
-
-macro_rules! push_insts {
-    ($program:expr, $scope:expr, $($inst:expr),*) => {
-        curr_func_mut!($program, $scope)
-            .layout_mut()
-            .bb_mut($scope.curr_bb())
-            .insts_mut()
-            .extend([$($inst),*]);
-    };
-}
+use crate::semantic::eval::{Consts, Evaluate};
+use crate::semantic::{ErrorKind, Result};
 
 #[derive(Debug, Clone, Copy)]
 pub enum SymbolValue {
     NeedLoad(Value),
     Value(Value),
     GlobalConst(i32),
+    // pointer to the storage, values are in `Scope::get_const_array`
+    ConstArray(Value),
 }
 
 impl SymbolValue {
-    pub fn into_value(self, program: &mut Program, scope: &mut Scope) -> Value {
+    pub fn into_value(self, builder: &mut IrBuilder) -> Value {
         match self {
-            SymbolValue::NeedLoad(value) => {
-                let v = new_value!(program, scope).load(value);
-                push_insts!(program, scope, v);
-                v
-            }
+            SymbolValue::NeedLoad(value) | SymbolValue::ConstArray(value) => builder.load(value),
             SymbolValue::Value(value) => value,
-            SymbolValue::GlobalConst(value) => new_value!(program, scope).integer(value),
+            SymbolValue::GlobalConst(value) => builder.integer(value),
         }
     }
 }
 
-/// if last inst isn't "Return", add jump inst and return true
-fn maybe_add_jump(program: &mut Program, scope: &mut Scope, jump_to: BasicBlock) {
-    if let Some(last_inst) = curr_func_mut!(program, scope)
-        .layout_mut()
-        .bb_mut(scope.curr_bb())
-        .insts()
-        .back_key()
-    {
-        let last_inst = last_inst.clone();
-        let last_inst_data = curr_func_mut!(program, scope).dfg().value(last_inst);
-        if let ValueKind::Return(_) = last_inst_data.kind() {
-        } else {
-            let jmp = new_value!(program, scope).jump(jump_to);
-            push_insts!(program, scope, jmp);
-        }
-    } else {
-        let jmp = new_value!(program, scope).jump(jump_to);
-        push_insts!(program, scope, jmp);
-    }
-}
-
-fn maybe_add_return(program: &mut Program, scope: &mut Scope) {
-    let last_inst = match curr_func_mut!(program, scope)
-        .layout_mut()
-        .bb_mut(scope.curr_bb())
-        .insts()
-        .back_key()
-    {
-        Some(last_inst) => last_inst.clone(),
-        None => {
-            let ret = new_value!(program, scope).ret(None);
-            push_insts!(program, scope, ret);
-            return;
-        }
-    };
-
-    let last_inst = last_inst.clone();
-    let last_inst_data = curr_func_mut!(program, scope).dfg().value(last_inst);
-    if let ValueKind::Return(_) = last_inst_data.kind() {
-    } else {
-        let ret = new_value!(program, scope).ret(None);
-        push_insts!(program, scope, ret);
+/// falling off the end of an int function returns 0
+fn maybe_add_return(builder: &mut IrBuilder) {
+    if builder.is_terminated() {
+        return;
     }
+
+    let ret_value = match builder.func().ty().kind() {
+        TypeKind::Function(_, ret_ty) if ret_ty.is_i32() => Some(builder.integer(0)),
+        _ => None,
+    };
+    builder.build(|b| b.ret(ret_value));
 }
 
 pub trait Generate {
@@ -122,9 +47,9 @@ pub trait Generate {
 
     fn generate<'ast>(
         &'ast self,
-        program: &mut Program,
+        builder: &mut IrBuilder,
         scope: &mut Scope<'ast>,
-    ) -> Result<Self::Out>;
+    ) -> Self::Out;
 }
 
 impl Generate for CompUnit {
@@ -132,88 +57,224 @@ impl Generate for CompUnit {
 
     fn generate<'ast>(
         &'ast self,
-        program: &mut Program,
+        builder: &mut IrBuilder,
         scope: &mut Scope<'ast>,
-    ) -> Result<Self::Out> {
-        dbg!(&self.global);
-        if let Some(ref comp_unit) = *self.comp_unit {
-            scope.reset_symbol_table();
-            comp_unit.generate(program, scope)?
+    ) -> Self::Out {
+        let globals = self.globals();
+        let defined: HashSet<&str> = globals
+            .iter()
+            .filter_map(|global| match global {
+                Global::FuncDef(func) => Some(func.ident.as_str()),
+                _ => None,
+            })
+            .collect();
+
+        // global variables and every signature first, so that bodies can call
+        // functions defined after them
+        for global in &globals {
+            match global {
+                Global::Decl(decl) => decl.generate(builder, scope),
+                Global::FuncDef(func) => {
+                    let (ty, params) = (&func.func_type, &func.params);
+                    declare_function(ty, &func.ident, params, true, builder, scope);
+                }
+                Global::FuncDecl(func) if !defined.contains(func.ident.as_str()) => {
+                    let (ty, params) = (&func.func_type, &func.params);
+                    declare_function(ty, &func.ident, params, false, builder, scope);
+                }
+                Global::FuncDecl(_) => {}
+            }
+        }
+        for global in globals {
+            if let Global::FuncDef(func) = global {
+                func.generate(builder, scope);
+                scope.reset_symbol_table();
+            }
         }
-        self.global.generate(program, scope)?;
-        scope.reset_symbol_table();
-        Ok(())
     }
 }
 
-fn param_to_ir_type(param: &FuncFParam) -> (Option<String>, Type) {
-    match param.btype {
-        BType::Int => (Some(format!("@{}", param.ident.clone())), Type::get_i32()),
+/// named constants as the IR holds them
+struct IrConsts<'a, 'ast> {
+    builder: &'a IrBuilder,
+    scope: &'a Scope<'ast>,
+}
+
+impl Consts for IrConsts<'_, '_> {
+    fn value(&mut self, lval: &LVal, indices: &[i32]) -> Result<i32> {
+        let value = match self.scope.get(&lval.ident) {
+            SymbolValue::Value(c) => self.builder.integer_value(c),
+            SymbolValue::GlobalConst(c) => Some(c),
+            SymbolValue::ConstArray(ptr) => self
+                .scope
+                .get_const_array(ptr)
+                .and_then(|array| array.get(indices)),
+            SymbolValue::NeedLoad(_) => None,
+        };
+        value.ok_or_else(|| ErrorKind::NotConst.at(lval.span))
     }
 }
 
-impl Generate for Global {
-    type Out = ();
+/// the semantic pass has made sure `exp` is a constant
+fn const_value(exp: &impl Evaluate, builder: &IrBuilder, scope: &Scope) -> i32 {
+    exp.eval(&mut IrConsts { builder, scope })
+        .expect("constant expressions are checked by the semantic pass")
+}
 
-    fn generate<'ast>(
-        &'ast self,
-        program: &mut Program,
-        scope: &mut Scope<'ast>,
-    ) -> Result<Self::Out> {
-        match self {
-            Global::FuncDef(func) => func.generate(program, scope),
-            Global::Decl(decl) => decl.generate(program, scope),
+/// lengths of the dimensions, all positive after the semantic pass
+fn array_dims(dims: &[ConstExp], builder: &IrBuilder, scope: &Scope) -> Vec<usize> {
+    dims.iter()
+        .map(|dim| const_value(dim, builder, scope) as usize)
+        .collect()
+}
+
+/// the semantic pass has made sure `init` fits
+fn flatten_init<'a, T: Initializer>(init: &'a T, dims: &[usize]) -> Vec<Option<&'a Exp>> {
+    flatten(init, dims)
+        .expect("initializers are checked by the semantic pass")
+        .elems
+}
+
+/// int a[2][3] => [[i32, 3], 2]
+fn array_type(dims: &[usize]) -> Type {
+    dims.iter()
+        .rev()
+        .fold(Type::get_i32(), |ty, &len| Type::get_array(ty, len))
+}
+
+/// build the constant initializer of a global, all zero (sub) arrays become zeroinit
+fn global_init(builder: &mut IrBuilder, dims: &[usize], values: &[i32]) -> Value {
+    match dims.split_first() {
+        None => builder.global_value().integer(values[0]),
+        Some(_) if values.iter().all(|&v| v == 0) => {
+            builder.global_value().zero_init(array_type(dims))
+        }
+        Some((_, sub_dims)) => {
+            let sub_size = sub_dims.iter().product();
+            let elems = values
+                .chunks(sub_size)
+                .map(|chunk| global_init(builder, sub_dims, chunk))
+                .collect();
+            builder.global_value().aggregate(elems)
         }
     }
 }
 
+/// store the flattened `values` into the local object `alloc` one by one
+fn init_local(
+    builder: &mut IrBuilder,
+    alloc: Value,
+    dims: &[usize],
+    values: Vec<Option<Value>>,
+) {
+    for (i, value) in values.into_iter().enumerate() {
+        let mut ptr = alloc;
+        let mut rest = i;
+        let mut indices = vec![0; dims.len()];
+        for (index, &dim) in indices.iter_mut().zip(dims).rev() {
+            *index = rest % dim;
+            rest /= dim;
+        }
+        for index in indices {
+            let index = builder.integer(index as i32);
+            ptr = builder.build(|b| b.get_elem_ptr(ptr, index));
+        }
+        let value = value.unwrap_or_else(|| builder.integer(0));
+        builder.store(value, ptr);
+    }
+}
+
+fn is_array_ptr(ty: &Type) -> bool {
+    match ty.kind() {
+        TypeKind::Pointer(base) => matches!(base.kind(), TypeKind::Array(_, _)),
+        _ => false,
+    }
+}
+
+/// the slot of an array parameter, which holds a pointer
+fn is_ptr_ptr(ty: &Type) -> bool {
+    match ty.kind() {
+        TypeKind::Pointer(base) => matches!(base.kind(), TypeKind::Pointer(_)),
+        _ => false,
+    }
+}
+
+/// int a[][5] => *[i32, 5]
+fn param_to_ir_type<'ast>(
+    param: &'ast FuncFParam,
+    builder: &mut IrBuilder,
+    scope: &mut Scope<'ast>,
+) -> Type {
+    match (param.btype, &param.dims) {
+        (BType::Int, None) => Type::get_i32(),
+        (BType::Int, Some(dims)) => {
+            let dims = array_dims(dims, builder, scope);
+            Type::get_pointer(array_type(&dims))
+        }
+    }
+}
+
+/// adds the function to the program, a body is filled in later only if
+/// `has_body`, otherwise it's an external `decl`
+fn declare_function<'ast>(
+    func_type: &FuncType,
+    ident: &'ast str,
+    params: &'ast [FuncFParam],
+    has_body: bool,
+    builder: &mut IrBuilder,
+    scope: &mut Scope<'ast>,
+) {
+    // a prototype of a builtin
+    if scope.global.function.contains_key(ident) {
+        return;
+    }
+    let mut params_ty = vec![];
+    for param in params {
+        params_ty.push(param_to_ir_type(param, builder, scope));
+    }
+    let return_ty = match func_type {
+        FuncType::Int => Type::get_i32(),
+        FuncType::Void => Type::get_unit(),
+    };
+    let name = format!("@{}", ident);
+    let data = if has_body {
+        // parameters are named with the body, when all globals are known
+        let params_ty = params_ty.into_iter().map(|ty| (None, ty)).collect();
+        FunctionData::with_param_names(name, params_ty, return_ty)
+    } else {
+        FunctionData::new_decl(name, params_ty, return_ty)
+    };
+    let func = builder.new_func(data);
+    scope.global.function.insert(ident, func);
+}
+
 impl Generate for FuncDef {
     type Out = ();
 
+    /// generates the body, the function is already declared
     fn generate<'ast>(
         &'ast self,
-        program: &mut Program,
+        builder: &mut IrBuilder,
         scope: &mut Scope<'ast>,
-    ) -> Result<Self::Out> {
-        let params_ty = self.params.iter().map(param_to_ir_type).collect();
-        let return_ty = match self.func_type {
-            FuncType::Int => Type::get_i32(),
-            FuncType::Void => Type::get_unit(),
-        };
-        let func = program.new_func(FunctionData::with_param_names(
-            format!("@{}", self.ident),
-            params_ty,
-            return_ty,
-        ));
-
-        scope.function = Some(func);
-        scope.global.function.insert(self.ident.as_str(), func);
-
-        let entry = new_bb!(program, scope).basic_block(Some("%entry".into()));
-        add_bb_to_program!(program, scope, entry);
-        scope.set_bb(entry);
+    ) -> Self::Out {
+        let func = scope.global.function[self.ident.as_str()];
+        builder.enter_function(func);
         scope.enter_scope();
 
-        for i in 0..self.params.len() {
-            let param = program.func(scope.function.unwrap()).params()[i].clone();
-            let data = curr_func_mut!(program, scope).dfg_mut().value(param);
-            let ty = data.ty().clone();
-            let name = data.name().clone().unwrap();
-            let name = name.trim_start_matches("@");
-            let p_var = new_value!(program, scope).alloc(ty);
-            curr_func_mut!(program, scope)
-                .dfg_mut()
-                .set_value_name(p_var, Some(format!("%{}", name)));
-            let store = new_value!(program, scope).store(param, p_var);
-            let load = new_value!(program, scope).load(p_var);
-            push_insts!(program, scope, p_var, store, load);
-            scope.add(self.params[i].ident.as_str(), SymbolValue::Value(load))?;
-        }
-
-        self.block.generate(program, scope)?;
-        maybe_add_return(program, scope);
+        for (i, param) in self.params.iter().enumerate() {
+            let value = builder.func().params()[i];
+            builder.set_name(value, &format!("@{}", param.ident));
+            let ty = builder.value_type(value);
+            let p_var = builder.alloc(ty, &format!("%{}", param.ident));
+            builder.store(value, p_var);
+            scope.add(param.ident.as_str(), SymbolValue::NeedLoad(p_var));
+        }
+
+        // the body shares the scope of the parameters
+        generate_items(&self.block.block_item, builder, scope);
+        maybe_add_return(builder);
+        builder.exit_function();
         scope.exit_scope();
-        Ok(())
     }
 }
 
@@ -222,15 +283,27 @@ impl Generate for Block {
 
     fn generate<'ast>(
         &'ast self,
-        program: &mut Program,
+        builder: &mut IrBuilder,
         scope: &mut Scope<'ast>,
-    ) -> Result<Self::Out> {
+    ) -> Self::Out {
         scope.enter_scope();
-        for item in &self.block_item {
-            item.generate(program, scope)?;
-        }
+        generate_items(&self.block_item, builder, scope);
         scope.exit_scope();
-        Ok(())
+    }
+}
+
+/// items after a `return`, `break` or `continue` can't be reached, and are
+/// dropped instead of following the terminator
+fn generate_items<'ast>(
+    items: &'ast [BlockItem],
+    builder: &mut IrBuilder,
+    scope: &mut Scope<'ast>,
+) {
+    for item in items {
+        if builder.is_terminated() {
+            break;
+        }
+        item.generate(builder, scope);
     }
 }
 
@@ -239,12 +312,12 @@ impl Generate for BlockItem {
 
     fn generate<'ast>(
         &'ast self,
-        program: &mut Program,
+        builder: &mut IrBuilder,
         scope: &mut Scope<'ast>,
-    ) -> Result<Self::Out> {
+    ) -> Self::Out {
         match self {
-            BlockItem::Decl(decl) => decl.generate(program, scope),
-            BlockItem::Stmt(stmt) => stmt.generate(program, scope),
+            BlockItem::Decl(decl) => decl.generate(builder, scope),
+            BlockItem::Stmt(stmt) => stmt.generate(builder, scope),
         }
     }
 }
@@ -254,12 +327,12 @@ impl Generate for Decl {
 
     fn generate<'ast>(
         &'ast self,
-        program: &mut Program,
+        builder: &mut IrBuilder,
         scope: &mut Scope<'ast>,
-    ) -> Result<Self::Out> {
+    ) -> Self::Out {
         match self {
-            Decl::ConstDecl(const_decl) => const_decl.generate(program, scope),
-            Decl::VarDecl(var_decl) => var_decl.generate(program, scope),
+            Decl::ConstDecl(const_decl) => const_decl.generate(builder, scope),
+            Decl::VarDecl(var_decl) => var_decl.generate(builder, scope),
         }
     }
 }
@@ -269,65 +342,51 @@ impl Generate for VarDecl {
 
     fn generate<'ast>(
         &'ast self,
-        program: &mut Program,
+        builder: &mut IrBuilder,
         scope: &mut Scope<'ast>,
-    ) -> Result<Self::Out> {
-        let return_type = match self.btype {
-            BType::Int => Type::get_i32(),
-        };
+    ) -> Self::Out {
         for def in &self.defs {
-            let return_type = return_type.clone();
-            match def {
-                VarDef::Id(id) => {
-                    if scope.is_curr_scope_exist(&id) {
-                        return Err(Error::Redeclare(id.to_string()));
-                    };
-
-                    if scope.in_global_scope() {
-                        let init = program.new_value().zero_init(return_type);
-                        let alloc = program.new_value().global_alloc(init);
-                        program.set_value_name(alloc, Some(format!("@{}", id)));
-                        scope.add_global_decl(&id, SymbolValue::NeedLoad(alloc))?;
-                        program.inst_layout().to_vec().extend([alloc]);
-                    } else {
-                        let var: Value = new_value!(program, scope).alloc(return_type);
-                        curr_func_mut!(program, scope)
-                            .dfg_mut()
-                            .set_value_name(var, Some(format!("@{}", id)));
-                        scope.add(&id, SymbolValue::NeedLoad(var))?;
-                        push_insts!(program, scope, var);
+            let dims = array_dims(&def.dims, builder, scope);
+            let ty = match self.btype {
+                BType::Int => array_type(&dims),
+            };
+            let init = def.init_val.as_ref().map(|init_val| flatten_init(init_val, &dims));
+
+            if scope.in_global_scope() {
+                let init = match init {
+                    Some(init) => {
+                        let values: Vec<_> = init
+                            .into_iter()
+                            .map(|exp| exp.map_or(0, |exp| const_value(exp, builder, scope)))
+                            .collect();
+                        global_init(builder, &dims, &values)
                     }
-                }
-                VarDef::Assign(id, init_val) => {
-                    if scope.is_curr_scope_exist(&id) {
-                        return Err(Error::Redeclare(id.to_string()));
-                    };
-                    if scope.in_global_scope() {
-                        let v = init_val.exp.eval(program, scope).unwrap();
-                        let value = program.new_value().integer(v);
-                        let alloc = program.new_value().global_alloc(value);
-                        program.set_value_name(alloc, Some(format!("@{}", id)));
-                        scope.add_global_decl(&id, SymbolValue::NeedLoad(alloc))?;
-                        program.inst_layout().to_vec().extend([alloc]);
-                    } else {
-                        let value = init_val
-                            .exp
-                            .generate(program, scope)?
-                            .into_value(program, scope);
-
-                        let alloc = new_value!(program, scope).alloc(return_type);
-                        curr_func_mut!(program, scope)
-                            .dfg_mut()
-                            .set_value_name(alloc, Some(format!("@{}", id)));
-
-                        let store_value = new_value!(program, scope).store(value, alloc);
-                        scope.add(&id, SymbolValue::NeedLoad(alloc))?;
-                        push_insts!(program, scope, alloc, store_value);
+                    None => builder.global_value().zero_init(ty),
+                };
+                let alloc = builder.global_value().global_alloc(init);
+                builder.set_global_name(alloc, format!("@{}", def.ident));
+                scope.add_global_decl(&def.ident, SymbolValue::NeedLoad(alloc));
+            } else {
+                let values = match init {
+                    Some(init) => {
+                        let mut values = vec![];
+                        for exp in init {
+                            let value = exp.map(|exp| exp.generate(builder, scope));
+                            values.push(value.map(|value| value.into_value(builder)));
+                        }
+                        Some(values)
                     }
+                    None => None,
+                };
+
+                let alloc = builder.alloc(ty, &format!("@{}", def.ident));
+
+                if let Some(values) = values {
+                    init_local(builder, alloc, &dims, values);
                 }
+                scope.add(&def.ident, SymbolValue::NeedLoad(alloc));
             }
         }
-        Ok(())
     }
 }
 
@@ -336,15 +395,14 @@ impl Generate for ConstDecl {
 
     fn generate<'ast>(
         &'ast self,
-        program: &mut Program,
+        builder: &mut IrBuilder,
         scope: &mut Scope<'ast>,
-    ) -> Result<Self::Out> {
+    ) -> Self::Out {
         match self.btype {
             BType::Int => {
                 for const_def in &self.defs {
-                    const_def.generate(program, scope)?
+                    const_def.generate(builder, scope)
                 }
-                Ok(())
             }
         }
     }
@@ -355,46 +413,47 @@ impl Generate for ConstDef {
 
     fn generate<'ast>(
         &'ast self,
-        program: &mut Program,
+        builder: &mut IrBuilder,
         scope: &mut Scope<'ast>,
-    ) -> Result<Self::Out> {
-        let r_val = self.const_init_val.generate(program, scope)?;
-        if scope.in_global_scope() {
-            scope.add_global_decl(&self.ident, SymbolValue::GlobalConst(r_val))
-        } else {
-            scope.add(
-                &self.ident,
-                SymbolValue::Value(new_value!(program, scope).integer(r_val)),
-            )
-        }
-    }
-}
-
-impl Generate for ConstInitVal {
-    type Out = i32;
-
-    fn generate<'ast>(
-        &'ast self,
-        program: &mut Program,
-        scope: &mut Scope<'ast>,
-    ) -> Result<Self::Out> {
-        match self {
-            ConstInitVal::ConstExp(const_exp) => const_exp.generate(program, scope),
+    ) -> Self::Out {
+        let dims = array_dims(&self.dims, builder, scope);
+        let values: Vec<_> = flatten_init(&self.const_init_val, &dims)
+            .into_iter()
+            .map(|exp| exp.map_or(0, |exp| const_value(exp, builder, scope)))
+            .collect();
+
+        if dims.is_empty() {
+            let r_val = values[0];
+            if scope.in_global_scope() {
+                scope.add_global_decl(&self.ident, SymbolValue::GlobalConst(r_val));
+            } else {
+                scope.add(
+                    &self.ident,
+                    SymbolValue::Value(builder.integer(r_val)),
+                );
+            }
+            return;
         }
-    }
-}
 
-impl Generate for ConstExp {
-    type Out = i32;
+        // const arrays still need memory for indexing with variables
+        let alloc = if scope.in_global_scope() {
+            let init = global_init(builder, &dims, &values);
+            let alloc = builder.global_value().global_alloc(init);
+            builder.set_global_name(alloc, format!("@{}", self.ident));
+            scope.add_global_decl(&self.ident, SymbolValue::ConstArray(alloc));
+            alloc
+        } else {
+            let alloc = builder.alloc(array_type(&dims), &format!("@{}", self.ident));
 
-    fn generate<'ast>(
-        &'ast self,
-        program: &mut Program,
-        scope: &mut Scope<'ast>,
-    ) -> Result<Self::Out> {
-        match self {
-            ConstExp::Exp(exp) => Ok(exp.eval(program, scope).unwrap()),
-        }
+            let mut init = vec![];
+            for &v in &values {
+                init.push(Some(builder.integer(v)));
+            }
+            init_local(builder, alloc, &dims, init);
+            scope.add(&self.ident, SymbolValue::ConstArray(alloc));
+            alloc
+        };
+        scope.add_const_array(alloc, ConstArray { dims, values });
     }
 }
 
@@ -403,154 +462,221 @@ impl Generate for Stmt {
 
     fn generate<'ast>(
         &'ast self,
-        program: &mut Program,
+        builder: &mut IrBuilder,
         scope: &mut Scope<'ast>,
-    ) -> Result<Self::Out> {
+    ) -> Self::Out {
         match self {
-            Stmt::Return(exp) => {
-                let return_val = if let Some(exp) = exp {
-                    Some(exp.generate(program, scope)?.into_value(program, scope))
-                } else {
-                    None
-                };
-                let ret = new_value!(program, scope).ret(return_val);
-                push_insts!(program, scope, ret);
-                Ok(())
+            Stmt::Return(exp, _) => {
+                let return_val = exp
+                    .as_ref()
+                    .map(|exp| exp.generate(builder, scope).into_value(builder));
+                builder.build(|b| b.ret(return_val));
             }
-            Stmt::Exp(exp) => {
+            Stmt::Exp(exp, _) => {
                 if let Some(exp) = exp {
-                    match exp.generate(program, scope) {
-                        Ok(_) => Ok(()),
-                        Err(err) => Err(err),
-                    }
-                } else {
-                    Ok(())
+                    exp.generate(builder, scope);
                 }
             }
-            Stmt::Block(block) => block.generate(program, scope),
-            Stmt::Assign(lval, exp) => {
-                let old_value = scope.get(&lval.ident)?;
-                match old_value {
-                    SymbolValue::NeedLoad(old) => {
-                        let new_val = exp.generate(program, scope)?.into_value(program, scope);
-                        let store = new_value!(program, scope).store(new_val, old);
-
-                        push_insts!(program, scope, store);
-                        Ok(())
-                    }
-                    SymbolValue::Value(_) => Err(Error::ReassignConst(lval.ident.clone())),
-                    SymbolValue::GlobalConst(_) => Err(Error::ReassignConst(lval.ident.clone())),
-                }
+            Stmt::Block(block) => block.generate(builder, scope),
+            Stmt::Assign(lval, exp, _) => {
+                // consts are never assigned after the semantic pass
+                let old = match lval.generate(builder, scope) {
+                    SymbolValue::NeedLoad(old) => old,
+                    _ => unreachable!("assignment to const: {}", lval.ident),
+                };
+                let new_val = exp.generate(builder, scope).into_value(builder);
+                builder.store(new_val, old);
             }
             Stmt::If(if_stmt) => {
-                let cond = if_stmt
-                    .cond
-                    .generate(program, scope)?
-                    .into_value(program, scope);
-
                 if let Some(else_stmt) = &if_stmt.else_then {
-                    let if_block = new_bb!(program, scope).basic_block(Some("%then".to_string()));
-                    add_bb_to_program!(program, scope, if_block);
-                    let else_block = new_bb!(program, scope).basic_block(Some("%else".to_string()));
-                    add_bb_to_program!(program, scope, else_block);
-                    let br_end = new_bb!(program, scope).basic_block(Some("%br_end".to_string()));
-                    add_bb_to_program!(program, scope, br_end);
-
-                    let br = new_value!(program, scope).branch(cond, if_block, else_block);
-                    push_insts!(program, scope, br);
+                    let if_block = builder.new_bb("%then");
+                    let else_block = builder.new_bb("%else");
+                    let br_end = builder.new_bb("%br_end");
+                    if_stmt.cond.generate_cond(builder, scope, if_block, else_block);
 
-                    scope.set_bb(if_block);
-                    if_stmt.if_then.generate(program, scope)?;
-                    maybe_add_jump(program, scope, br_end);
+                    builder.set_insert_point(if_block);
+                    if_stmt.if_then.generate(builder, scope);
+                    builder.fall_through(br_end);
 
-                    scope.set_bb(else_block);
-                    else_stmt.generate(program, scope)?;
-                    maybe_add_jump(program, scope, br_end);
+                    builder.set_insert_point(else_block);
+                    else_stmt.generate(builder, scope);
+                    builder.fall_through(br_end);
 
-                    scope.set_bb(br_end);
+                    builder.set_insert_point(br_end);
                 } else {
-                    let if_block = new_bb!(program, scope).basic_block(Some("%then".to_string()));
-                    add_bb_to_program!(program, scope, if_block);
+                    let if_block = builder.new_bb("%then");
+                    let br_end = builder.new_bb("%br_end");
+                    if_stmt.cond.generate_cond(builder, scope, if_block, br_end);
 
-                    let br_end = new_bb!(program, scope).basic_block(Some("%br_end".to_string()));
-                    add_bb_to_program!(program, scope, br_end);
+                    builder.set_insert_point(if_block);
+                    if_stmt.if_then.generate(builder, scope);
+                    builder.fall_through(br_end);
 
-                    let br = new_value!(program, scope).branch(cond, if_block, br_end);
-                    push_insts!(program, scope, br);
-
-                    scope.set_bb(if_block);
-                    if_stmt.if_then.generate(program, scope)?;
-                    maybe_add_jump(program, scope, br_end);
-
-                    scope.set_bb(br_end);
+                    builder.set_insert_point(br_end);
                 }
-                Ok(())
             }
             Stmt::While(while_stmt) => {
-                let while_cond =
-                    new_bb!(program, scope).basic_block(Some("%while_cond".to_string()));
-                add_bb_to_program!(program, scope, while_cond);
-                let jump = new_value!(program, scope).jump(while_cond);
-                push_insts!(program, scope, jump);
-
-                let while_body =
-                    new_bb!(program, scope).basic_block(Some("%while_body".to_string()));
-                add_bb_to_program!(program, scope, while_body);
-                let while_end = new_bb!(program, scope).basic_block(Some("%while_end".to_string()));
-                add_bb_to_program!(program, scope, while_end);
+                let while_cond = builder.new_bb("%while_cond");
+                builder.build(|b| b.jump(while_cond));
+                let while_body = builder.new_bb("%while_body");
+                let while_end = builder.new_bb("%while_end");
 
                 scope.enter_loop(while_cond, while_end);
-                scope.set_bb(while_cond);
-
-                let cond = while_stmt
-                    .cond
-                    .generate(program, scope)?
-                    .into_value(program, scope);
-                let br = new_value!(program, scope).branch(cond, while_body, while_end);
-                push_insts!(program, scope, br);
+                // `continue` and the end of the body jump back to it
+                builder.set_insert_point_unsealed(while_cond);
+                while_stmt.cond.generate_cond(builder, scope, while_body, while_end);
 
-                scope.set_bb(while_body);
-                while_stmt.body.generate(program, scope)?;
-                maybe_add_jump(program, scope, while_cond);
+                builder.set_insert_point(while_body);
+                while_stmt.body.generate(builder, scope);
+                builder.fall_through(while_cond);
+                builder.seal(while_cond);
 
                 scope.exit_loop();
-                scope.set_bb(while_end);
-                Ok(())
+                builder.set_insert_point(while_end);
             }
-            Stmt::Break => {
-                let loop_block = scope.get_loop_block()?;
-                let jump = new_value!(program, scope).jump(loop_block.exit);
-                push_insts!(program, scope, jump);
-
-                let bb = new_bb!(program, scope).basic_block(Some("%after_break".to_string()));
-                add_bb_to_program!(program, scope, bb);
-                scope.set_bb(bb);
-                Ok(())
+            Stmt::Break(_) => {
+                let loop_block = scope.get_loop_block();
+                builder.build(|b| b.jump(loop_block.exit));
             }
-            Stmt::Continue => {
-                let loop_block = scope.get_loop_block()?;
-                let jump = new_value!(program, scope).jump(loop_block.entry);
-                push_insts!(program, scope, jump);
-
-                let bb = new_bb!(program, scope).basic_block(Some("%after_continue".to_string()));
-                add_bb_to_program!(program, scope, bb);
-                scope.set_bb(bb);
-                Ok(())
+            Stmt::Continue(_) => {
+                let loop_block = scope.get_loop_block();
+                builder.build(|b| b.jump(loop_block.entry));
             }
         }
     }
 }
 
+/// generates a condition as a branch to `true_bb` or `false_bb`, `&&`, `||`
+/// and `!` jump straight to the targets instead of computing a boolean
+pub trait GenerateCond {
+    fn generate_cond<'ast>(
+        &'ast self,
+        builder: &mut IrBuilder,
+        scope: &mut Scope<'ast>,
+        true_bb: BasicBlock,
+        false_bb: BasicBlock,
+    );
+}
+
+/// branches on the value of `exp`, nonzero is true
+fn branch_on<'ast>(
+    exp: &'ast impl Generate<Out = SymbolValue>,
+    builder: &mut IrBuilder,
+    scope: &mut Scope<'ast>,
+    true_bb: BasicBlock,
+    false_bb: BasicBlock,
+) {
+    let cond = exp.generate(builder, scope).into_value(builder);
+    builder.build(|b| b.branch(cond, true_bb, false_bb));
+}
+
+impl GenerateCond for Exp {
+    fn generate_cond<'ast>(
+        &'ast self,
+        builder: &mut IrBuilder,
+        scope: &mut Scope<'ast>,
+        true_bb: BasicBlock,
+        false_bb: BasicBlock,
+    ) {
+        match self {
+            Exp::LOrExp(exp) => exp.generate_cond(builder, scope, true_bb, false_bb),
+        }
+    }
+}
+
+impl GenerateCond for LOrExp {
+    fn generate_cond<'ast>(
+        &'ast self,
+        builder: &mut IrBuilder,
+        scope: &mut Scope<'ast>,
+        true_bb: BasicBlock,
+        false_bb: BasicBlock,
+    ) {
+        match self {
+            LOrExp::LAndExp(land_exp) => {
+                land_exp.generate_cond(builder, scope, true_bb, false_bb)
+            }
+            LOrExp::LOrExp(lor_exp, land_exp, _) => {
+                let or_false = builder.new_bb("%lor_false");
+                lor_exp.generate_cond(builder, scope, true_bb, or_false);
+                builder.set_insert_point(or_false);
+                land_exp.generate_cond(builder, scope, true_bb, false_bb)
+            }
+        }
+    }
+}
+
+impl GenerateCond for LAndExp {
+    fn generate_cond<'ast>(
+        &'ast self,
+        builder: &mut IrBuilder,
+        scope: &mut Scope<'ast>,
+        true_bb: BasicBlock,
+        false_bb: BasicBlock,
+    ) {
+        match self {
+            LAndExp::EqExp(eq_exp) => eq_exp.generate_cond(builder, scope, true_bb, false_bb),
+            LAndExp::LAndExp(land_exp, eq_exp, _) => {
+                let and_true = builder.new_bb("%land_true");
+                land_exp.generate_cond(builder, scope, and_true, false_bb);
+                builder.set_insert_point(and_true);
+                eq_exp.generate_cond(builder, scope, true_bb, false_bb)
+            }
+        }
+    }
+}
+
+impl GenerateCond for EqExp {
+    fn generate_cond<'ast>(
+        &'ast self,
+        builder: &mut IrBuilder,
+        scope: &mut Scope<'ast>,
+        true_bb: BasicBlock,
+        false_bb: BasicBlock,
+    ) {
+        // a lone unary expression may still be a `!` or a parenthesized condition
+        match self {
+            EqExp::RelExp(RelExp::AddExp(AddExp::MulExp(MulExp::UnaryExp(unary_exp)))) => {
+                unary_exp.generate_cond(builder, scope, true_bb, false_bb)
+            }
+            _ => branch_on(self, builder, scope, true_bb, false_bb),
+        }
+    }
+}
+
+impl GenerateCond for UnaryExp {
+    fn generate_cond<'ast>(
+        &'ast self,
+        builder: &mut IrBuilder,
+        scope: &mut Scope<'ast>,
+        true_bb: BasicBlock,
+        false_bb: BasicBlock,
+    ) {
+        match self {
+            UnaryExp::UnaryOp(UnaryOp::Not, exp, _) => {
+                exp.generate_cond(builder, scope, false_bb, true_bb)
+            }
+            UnaryExp::UnaryOp(UnaryOp::Add, exp, _) => {
+                exp.generate_cond(builder, scope, true_bb, false_bb)
+            }
+            UnaryExp::PrimaryExp(PrimaryExp::Expression(exp)) => {
+                exp.generate_cond(builder, scope, true_bb, false_bb)
+            }
+            _ => branch_on(self, builder, scope, true_bb, false_bb),
+        }
+    }
+}
+
 impl Generate for Exp {
     type Out = SymbolValue;
 
     fn generate<'ast>(
         &'ast self,
-        program: &mut Program,
+        builder: &mut IrBuilder,
         scope: &mut Scope<'ast>,
-    ) -> Result<Self::Out> {
+    ) -> Self::Out {
         match self {
-            Exp::LOrExp(exp) => exp.generate(program, scope),
+            Exp::LOrExp(exp) => exp.generate(builder, scope),
         }
     }
 }
@@ -560,40 +686,32 @@ impl Generate for LOrExp {
 
     fn generate<'ast>(
         &'ast self,
-        program: &mut Program,
+        builder: &mut IrBuilder,
         scope: &mut Scope<'ast>,
-    ) -> Result<Self::Out> {
+    ) -> Self::Out {
         match self {
-            LOrExp::LAndExp(land_exp) => land_exp.generate(program, scope),
-            LOrExp::LOrExp(lor_exp, land_exp) => {
-                let or_false = new_bb!(program, scope).basic_block(Some("%lor_false".to_string()));
-                add_bb_to_program!(program, scope, or_false);
-                let or_end = new_bb!(program, scope).basic_block(Some("%lor_end".to_string()));
-                add_bb_to_program!(program, scope, or_end);
-                let zero = new_value!(program, scope).integer(0);
-
-                let result = new_value!(program, scope).alloc(Type::get_i32());
-                curr_func_mut!(program, scope)
-                    .dfg_mut()
-                    .set_value_name(result, Some("%logic_result".to_string()));
-
-                let lhs = lor_exp.generate(program, scope)?.into_value(program, scope);
-                let not_eq1 = new_value!(program, scope).binary(BinaryOp::NotEq, lhs, zero);
-                let store_to_result = new_value!(program, scope).store(not_eq1, result);
-                let br = new_value!(program, scope).branch(not_eq1, or_end, or_false);
-                push_insts!(program, scope, not_eq1, result, store_to_result, br);
-
-                scope.set_bb(or_false);
+            LOrExp::LAndExp(land_exp) => land_exp.generate(builder, scope),
+            LOrExp::LOrExp(lor_exp, land_exp, _) => {
+                let or_false = builder.new_bb("%lor_false");
+                let or_end = builder.new_bb("%lor_end");
+                let zero = builder.integer(0);
+
+                let lhs = lor_exp.generate(builder, scope).into_value(builder);
+                let not_eq1 = builder.build(|b| b.binary(BinaryOp::NotEq, lhs, zero));
+                let result = builder.alloc(Type::get_i32(), "%logic_result");
+                builder.store(not_eq1, result);
+                builder.build(|b| b.branch(not_eq1, or_end, or_false));
+
+                builder.set_insert_point(or_false);
                 let rhs = land_exp
-                    .generate(program, scope)?
-                    .into_value(program, scope);
-                let not_eq2 = new_value!(program, scope).binary(BinaryOp::NotEq, rhs, zero);
-                let store_to_result2 = new_value!(program, scope).store(not_eq2, result);
-                let jump = new_value!(program, scope).jump(or_end);
-                push_insts!(program, scope, not_eq2, store_to_result2, jump);
-
-                scope.set_bb(or_end);
-                Ok(SymbolValue::NeedLoad(result))
+                    .generate(builder, scope)
+                    .into_value(builder);
+                let not_eq2 = builder.build(|b| b.binary(BinaryOp::NotEq, rhs, zero));
+                builder.store(not_eq2, result);
+                builder.build(|b| b.jump(or_end));
+
+                builder.set_insert_point(or_end);
+                SymbolValue::NeedLoad(result)
             }
         }
     }
@@ -604,41 +722,32 @@ impl Generate for LAndExp {
 
     fn generate<'ast>(
         &'ast self,
-        program: &mut Program,
+        builder: &mut IrBuilder,
         scope: &mut Scope<'ast>,
-    ) -> Result<Self::Out> {
+    ) -> Self::Out {
         match self {
-            LAndExp::EqExp(eq_exp) => eq_exp.generate(program, scope),
-            LAndExp::LAndExp(land_exp, eq_exp) => {
-                let and_true = new_bb!(program, scope).basic_block(Some("%land_true".to_string()));
-                add_bb_to_program!(program, scope, and_true);
-                let and_end = new_bb!(program, scope).basic_block(Some("%land_end".to_string()));
-                add_bb_to_program!(program, scope, and_end);
-                let zero = new_value!(program, scope).integer(0);
-
-                let result = new_value!(program, scope).alloc(Type::get_i32());
-                curr_func_mut!(program, scope)
-                    .dfg_mut()
-                    .set_value_name(result, Some("%logic_result".to_string()));
+            LAndExp::EqExp(eq_exp) => eq_exp.generate(builder, scope),
+            LAndExp::LAndExp(land_exp, eq_exp, _) => {
+                let and_true = builder.new_bb("%land_true");
+                let and_end = builder.new_bb("%land_end");
+                let zero = builder.integer(0);
 
                 let lhs = land_exp
-                    .generate(program, scope)?
-                    .into_value(program, scope);
-                let not_eq1 = new_value!(program, scope).binary(BinaryOp::NotEq, lhs, zero);
-                let store_to_result = new_value!(program, scope).store(zero, result);
-                let br = new_value!(program, scope).branch(not_eq1, and_true, and_end);
-                push_insts!(program, scope, not_eq1, result, store_to_result, br);
-
-                scope.set_bb(and_true);
-                let rhs = eq_exp.generate(program, scope)?.into_value(program, scope);
-                let not_eq2 = new_value!(program, scope).binary(BinaryOp::NotEq, rhs, zero);
-                let store_to_result2 = new_value!(program, scope).store(not_eq2, result);
-                let jump = new_value!(program, scope).jump(and_end);
-
-                push_insts!(program, scope, not_eq2, store_to_result2, jump);
-
-                scope.set_bb(and_end);
-                Ok(SymbolValue::NeedLoad(result))
+                    .generate(builder, scope)
+                    .into_value(builder);
+                let not_eq1 = builder.build(|b| b.binary(BinaryOp::NotEq, lhs, zero));
+                let result = builder.alloc(Type::get_i32(), "%logic_result");
+                builder.store(zero, result);
+                builder.build(|b| b.branch(not_eq1, and_true, and_end));
+
+                builder.set_insert_point(and_true);
+                let rhs = eq_exp.generate(builder, scope).into_value(builder);
+                let not_eq2 = builder.build(|b| b.binary(BinaryOp::NotEq, rhs, zero));
+                builder.store(not_eq2, result);
+                builder.build(|b| b.jump(and_end));
+
+                builder.set_insert_point(and_end);
+                SymbolValue::NeedLoad(result)
             }
         }
     }
@@ -649,24 +758,20 @@ impl Generate for EqExp {
 
     fn generate<'ast>(
         &'ast self,
-        program: &mut Program,
+        builder: &mut IrBuilder,
         scope: &mut Scope<'ast>,
-    ) -> Result<Self::Out> {
+    ) -> Self::Out {
         match self {
-            EqExp::RelExp(rel_exp) => rel_exp.generate(program, scope),
-            EqExp::EqExp(eq_exp, eq_op, rel_exp) => {
-                let lhs = eq_exp.generate(program, scope)?.into_value(program, scope);
-                let rhs = rel_exp.generate(program, scope)?.into_value(program, scope);
+            EqExp::RelExp(rel_exp) => rel_exp.generate(builder, scope),
+            EqExp::EqExp(eq_exp, eq_op, rel_exp, _) => {
+                let lhs = eq_exp.generate(builder, scope).into_value(builder);
+                let rhs = rel_exp.generate(builder, scope).into_value(builder);
                 let op = match eq_op {
                     EqOp::Eq => BinaryOp::Eq,
                     EqOp::NotEq => BinaryOp::NotEq,
                 };
-                let inst = curr_func_mut!(program, scope)
-                    .dfg_mut()
-                    .new_value()
-                    .binary(op, lhs, rhs);
-                push_insts!(program, scope, inst);
-                Ok(SymbolValue::Value(inst))
+                let inst = builder.build(|b| b.binary(op, lhs, rhs));
+                SymbolValue::Value(inst)
             }
         }
     }
@@ -677,26 +782,22 @@ impl Generate for RelExp {
 
     fn generate<'ast>(
         &'ast self,
-        program: &mut Program,
+        builder: &mut IrBuilder,
         scope: &mut Scope<'ast>,
-    ) -> Result<Self::Out> {
+    ) -> Self::Out {
         match self {
-            RelExp::AddExp(add_exp) => add_exp.generate(program, scope),
-            RelExp::RelExp(rel_exp, rel_op, add_exp) => {
-                let lhs = rel_exp.generate(program, scope)?.into_value(program, scope);
-                let rhs = add_exp.generate(program, scope)?.into_value(program, scope);
+            RelExp::AddExp(add_exp) => add_exp.generate(builder, scope),
+            RelExp::RelExp(rel_exp, rel_op, add_exp, _) => {
+                let lhs = rel_exp.generate(builder, scope).into_value(builder);
+                let rhs = add_exp.generate(builder, scope).into_value(builder);
                 let op = match rel_op {
                     RelOp::Gt => BinaryOp::Gt,
                     RelOp::Lt => BinaryOp::Lt,
                     RelOp::Ge => BinaryOp::Ge,
                     RelOp::Le => BinaryOp::Le,
                 };
-                let inst = curr_func_mut!(program, scope)
-                    .dfg_mut()
-                    .new_value()
-                    .binary(op, lhs, rhs);
-                push_insts!(program, scope, inst);
-                Ok(SymbolValue::Value(inst))
+                let inst = builder.build(|b| b.binary(op, lhs, rhs));
+                SymbolValue::Value(inst)
             }
         }
     }
@@ -707,24 +808,20 @@ impl Generate for AddExp {
 
     fn generate<'ast>(
         &'ast self,
-        program: &mut Program,
+        builder: &mut IrBuilder,
         scope: &mut Scope<'ast>,
-    ) -> Result<Self::Out> {
+    ) -> Self::Out {
         match self {
-            AddExp::MulExp(mul_exp) => mul_exp.generate(program, scope),
-            AddExp::AddAndMul(add_exp, add_op, mul_exp) => {
-                let lhs = add_exp.generate(program, scope)?.into_value(program, scope);
-                let rhs = mul_exp.generate(program, scope)?.into_value(program, scope);
+            AddExp::MulExp(mul_exp) => mul_exp.generate(builder, scope),
+            AddExp::AddAndMul(add_exp, add_op, mul_exp, _) => {
+                let lhs = add_exp.generate(builder, scope).into_value(builder);
+                let rhs = mul_exp.generate(builder, scope).into_value(builder);
                 let op = match add_op {
                     AddOp::Add => BinaryOp::Add,
                     AddOp::Sub => BinaryOp::Sub,
                 };
-                let inst = curr_func_mut!(program, scope)
-                    .dfg_mut()
-                    .new_value()
-                    .binary(op, lhs, rhs);
-                push_insts!(program, scope, inst);
-                Ok(SymbolValue::Value(inst))
+                let inst = builder.build(|b| b.binary(op, lhs, rhs));
+                SymbolValue::Value(inst)
             }
         }
     }
@@ -735,27 +832,23 @@ impl Generate for MulExp {
 
     fn generate<'ast>(
         &'ast self,
-        program: &mut Program,
+        builder: &mut IrBuilder,
         scope: &mut Scope<'ast>,
-    ) -> Result<Self::Out> {
+    ) -> Self::Out {
         match self {
-            MulExp::UnaryExp(unary_exp) => unary_exp.generate(program, scope),
-            MulExp::MulAndUnary(mul_exp, mul_op, unary_exp) => {
-                let lhs = mul_exp.generate(program, scope)?.into_value(program, scope);
+            MulExp::UnaryExp(unary_exp) => unary_exp.generate(builder, scope),
+            MulExp::MulAndUnary(mul_exp, mul_op, unary_exp, _) => {
+                let lhs = mul_exp.generate(builder, scope).into_value(builder);
                 let rhs = unary_exp
-                    .generate(program, scope)?
-                    .into_value(program, scope);
+                    .generate(builder, scope)
+                    .into_value(builder);
                 let op = match mul_op {
                     MulOp::Mul => BinaryOp::Mul,
                     MulOp::Div => BinaryOp::Div,
                     MulOp::Mod => BinaryOp::Mod,
                 };
-                let inst = curr_func_mut!(program, scope)
-                    .dfg_mut()
-                    .new_value()
-                    .binary(op, lhs, rhs);
-                push_insts!(program, scope, inst);
-                Ok(SymbolValue::Value(inst))
+                let inst = builder.build(|b| b.binary(op, lhs, rhs));
+                SymbolValue::Value(inst)
             }
         }
     }
@@ -766,50 +859,44 @@ impl Generate for UnaryExp {
 
     fn generate<'ast>(
         &'ast self,
-        program: &mut Program,
+        builder: &mut IrBuilder,
         scope: &mut Scope<'ast>,
-    ) -> Result<Self::Out> {
+    ) -> Self::Out {
         match self {
-            UnaryExp::PrimaryExp(primary_exp) => primary_exp.generate(program, scope),
-            UnaryExp::UnaryOp(unary_op, unary_exp) => match unary_op {
-                UnaryOp::Add => unary_exp.generate(program, scope),
+            UnaryExp::PrimaryExp(primary_exp) => primary_exp.generate(builder, scope),
+            UnaryExp::UnaryOp(unary_op, unary_exp, _) => match unary_op {
+                UnaryOp::Add => unary_exp.generate(builder, scope),
                 UnaryOp::Minus => {
+                    // negative literals, -2147483648 among them, are folded
+                    if let UnaryExp::PrimaryExp(PrimaryExp::Number(n, _)) = unary_exp.as_ref() {
+                        let value = builder.integer((*n as i32).wrapping_neg());
+                        return SymbolValue::Value(value);
+                    }
                     let l_value = unary_exp
-                        .generate(program, scope)?
-                        .into_value(program, scope);
-                    let r_value = new_value!(program, scope).integer(0);
-                    let inst = new_value!(program, scope).binary(BinaryOp::Sub, r_value, l_value);
-                    push_insts!(program, scope, inst);
-                    Ok(SymbolValue::Value(inst))
+                        .generate(builder, scope)
+                        .into_value(builder);
+                    let r_value = builder.integer(0);
+                    let inst = builder.build(|b| b.binary(BinaryOp::Sub, r_value, l_value));
+                    SymbolValue::Value(inst)
                 }
                 UnaryOp::Not => {
                     let l_value = unary_exp
-                        .generate(program, scope)?
-                        .into_value(program, scope);
-                    let r_value = new_value!(program, scope).integer(0);
-                    let inst = new_value!(program, scope).binary(BinaryOp::Eq, r_value, l_value);
-                    push_insts!(program, scope, inst);
-                    Ok(SymbolValue::Value(inst))
+                        .generate(builder, scope)
+                        .into_value(builder);
+                    let r_value = builder.integer(0);
+                    let inst = builder.build(|b| b.binary(BinaryOp::Eq, r_value, l_value));
+                    SymbolValue::Value(inst)
                 }
             },
             UnaryExp::Call(func_call) => {
-                match scope.global.function.get(func_call.ident.as_str()) {
-                    Some(func) => {
-                        let func = func.clone();
-                        let mut args = vec![];
-                        for exp in &func_call.args {
-                            let arg = exp.generate(program, scope)?.into_value(program, scope);
-                            args.push(arg);
-                        }
-                        let call = new_value!(program, scope).call(func, args);
-                        push_insts!(program, scope, call);
-                        Ok(SymbolValue::Value(call))
-                    }
-                    None => Err(Undefined(format!(
-                        "Function Undefined: {:?}",
-                        func_call.ident
-                    ))),
+                let func = scope.global.function[func_call.ident.as_str()];
+                let mut args = vec![];
+                for exp in &func_call.args {
+                    let arg = exp.generate(builder, scope).into_value(builder);
+                    args.push(arg);
                 }
+                let call = builder.build(|b| b.call(func, args));
+                SymbolValue::Value(call)
             }
         }
     }
@@ -820,16 +907,61 @@ impl Generate for PrimaryExp {
 
     fn generate<'ast>(
         &'ast self,
-        program: &mut Program,
+        builder: &mut IrBuilder,
         scope: &mut Scope<'ast>,
-    ) -> Result<Self::Out> {
+    ) -> Self::Out {
         match self {
-            PrimaryExp::Expression(exp) => exp.generate(program, scope),
-            PrimaryExp::Number(n) => Ok(SymbolValue::Value(new_value!(program, scope).integer(*n))),
-            PrimaryExp::LVal(lval) => match scope.get(&lval.ident) {
-                Ok(v) => Ok(v),
-                Err(err) => Err(err),
+            PrimaryExp::Expression(exp) => exp.generate(builder, scope),
+            PrimaryExp::Number(n, _) => SymbolValue::Value(builder.integer(*n as i32)),
+            PrimaryExp::LVal(lval) => match lval.generate(builder, scope) {
+                SymbolValue::NeedLoad(ptr) => {
+                    SymbolValue::Value(builder.read(ptr, &lval.ident, lval.span))
+                }
+                symbol => symbol,
             },
         }
     }
 }
+
+impl Generate for LVal {
+    type Out = SymbolValue;
+
+    /// returns `NeedLoad` of the element pointer for scalar,
+    /// arrays decay to the pointer of their first element
+    fn generate<'ast>(
+        &'ast self,
+        builder: &mut IrBuilder,
+        scope: &mut Scope<'ast>,
+    ) -> Self::Out {
+        let symbol = scope.get(&self.ident);
+        let mut ptr = match symbol {
+            SymbolValue::NeedLoad(ptr) | SymbolValue::ConstArray(ptr) => ptr,
+            // scalar consts, never indexed after the semantic pass
+            SymbolValue::Value(_) | SymbolValue::GlobalConst(_) => return symbol,
+        };
+
+        let mut indices = self.indices.iter();
+        if is_ptr_ptr(&builder.value_type(ptr)) {
+            ptr = builder.load(ptr);
+            match indices.next() {
+                Some(index) => {
+                    let index = index.generate(builder, scope).into_value(builder);
+                    ptr = builder.build(|b| b.get_ptr(ptr, index));
+                }
+                None => return SymbolValue::Value(ptr),
+            }
+        }
+        for index in indices {
+            let index = index.generate(builder, scope).into_value(builder);
+            ptr = builder.build(|b| b.get_elem_ptr(ptr, index));
+        }
+
+        if is_array_ptr(&builder.value_type(ptr)) {
+            let zero = builder.integer(0);
+            let first = builder.build(|b| b.get_elem_ptr(ptr, zero));
+            SymbolValue::Value(first)
+        } else {
+            SymbolValue::NeedLoad(ptr)
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use crate::ast::{ConstExp, ConstInitVal, Exp, InitVal, Span};
+
+/// the reason why an initializer doesn't fit
+type Result<T> = std::result::Result<T, &'static str>;
+
+pub enum InitItem<'a, T> {
+    Exp(&'a Exp),
+    List(&'a [T], Span),
+}
+
+/// common shape of `InitVal` and `ConstInitVal`
+pub trait Initializer: Sized {
+    fn item(&self) -> InitItem<'_, Self>;
+
+    fn span(&self) -> Span {
+        match self.item() {
+            InitItem::Exp(exp) => exp.span(),
+            InitItem::List(_, span) => span,
+        }
+    }
+}
+
+impl Initializer for InitVal {
+    fn item(&self) -> InitItem<'_, Self> {
+        match self {
+            InitVal::Exp(exp) => InitItem::Exp(exp),
+            InitVal::List(list, span) => InitItem::List(list, *span),
+        }
+    }
+}
+
+impl Initializer for ConstInitVal {
+    fn item(&self) -> InitItem<'_, Self> {
+        match self {
+            ConstInitVal::ConstExp(ConstExp::Exp(exp)) => InitItem::Exp(exp),
+            ConstInitVal::List(list, span) => InitItem::List(list, *span),
+        }
+    }
+}
+
+/// an initializer in the full shape of the object
+pub struct Flattened<'a> {
+    // row-major, `None` means the element is implicitly zero
+    pub elems: Vec<Option<&'a Exp>>,
+    // items dropped from braced lists at scalar positions
+    pub excess: Vec<Span>,
+}
+
+/// normalizes an initializer to the full shape of `dims` in row-major order
+///
+/// int a[2][3] = {1, {2, 3}, 4} => [1, 2, 4, 0, 0, 0], `3` is excess
+///
+/// a braced list starts the biggest sub array aligned at the current position,
+/// like gcc, a braced list at a scalar position only takes its first element
+pub fn flatten<'a, T: Initializer>(init: &'a T, dims: &[usize]) -> Result<Flattened<'a>> {
+    let mut out = Flattened {
+        elems: vec![],
+        excess: vec![],
+    };
+    match init.item() {
+        InitItem::Exp(exp) if dims.is_empty() => out.elems.push(Some(exp)),
+        InitItem::Exp(_) => {
+            return Err("array must be initialized by a braced list")
+        }
+        InitItem::List(list, _) => flatten_list(list, dims, &mut out)?,
+    }
+    Ok(out)
+}
+
+fn flatten_list<'a, T: Initializer>(
+    list: &'a [T],
+    dims: &[usize],
+    out: &mut Flattened<'a>,
+) -> Result<()> {
+    let total: usize = dims.iter().product();
+    let start = out.elems.len();
+    for item in list {
+        let filled = out.elems.len() - start;
+        if filled >= total {
+            if dims.is_empty() {
+                out.excess.push(item.span());
+                continue;
+            }
+            return Err("excess elements in array initializer");
+        }
+        match item.item() {
+            InitItem::Exp(exp) => out.elems.push(Some(exp)),
+            InitItem::List(sub_list, _) => {
+                let sub_dims = (1..dims.len())
+                    .map(|i| &dims[i..])
+                    .find(|sub_dims| filled.is_multiple_of(sub_dims.iter().product()))
+                    .unwrap_or(&[]);
+                flatten_list(sub_list, sub_dims, out)?;
+            }
+        }
+    }
+    out.elems.resize(start + total, None);
+    Ok(())
+}
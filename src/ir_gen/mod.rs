@@ -1,96 +1,108 @@
 use std::vec;
 
-use koopa::ir::{FunctionData, Program, Type};
+use koopa::ir::{Function, FunctionData, Program, Type, Value};
 
+use builder::IrBuilder;
 use gen::Generate;
 use scope::Scope;
 
-use crate::ast::CompUnit;
+use crate::ast::{CompUnit, Span};
 use crate::ir_gen::scope::Global;
 
-mod eval;
+mod builder;
 mod gen;
+pub(crate) mod init;
 mod scope;
+mod ssa;
 
-#[derive(Debug)]
-pub enum Error {
-    Error(String),
-    ReassignConst(String),
-    Redeclare(String),
-    NoInLoop,
-    Undefined(String),
+/// how the koopa IR is generated
+#[derive(Default)]
+pub struct Options {
+    // keep scalar locals in SSA values instead of allocs
+    pub ssa: bool,
 }
 
-pub type Result<T> = std::result::Result<T, Error>;
+impl Options {
+    /// picks -ssa out of the command line
+    pub fn from_args(args: &[String]) -> Self {
+        Options {
+            ssa: args.iter().any(|arg| arg == "-ssa"),
+        }
+    }
+}
+
+/// a load of a variable and where the source reads it
+pub struct Read {
+    pub func: Function,
+    pub load: Value,
+    pub name: String,
+    pub span: Span,
+}
 
-pub fn generate_program(comp_unit: &CompUnit) -> Result<Program> {
-    let mut program = Program::new();
-    let mut scope = Scope::new(None, Global::new(), Vec::new());
+/// the program and the loads of variables the source reads, for the
+/// warnings which look at the IR, `comp_unit` must have passed the semantic
+/// checks
+pub fn generate_program(comp_unit: &CompUnit, options: &Options) -> (Program, Vec<Read>) {
+    let mut builder = IrBuilder::new(options.ssa);
+    let mut scope = Scope::new(Global::new(), Vec::new());
 
-    init_bulidin_func(&mut program, &mut scope);
+    init_bulidin_func(&mut builder, &mut scope);
 
-    comp_unit.generate(&mut program, &mut scope)?;
-    Ok(program)
+    comp_unit.generate(&mut builder, &mut scope);
+    builder.into_program()
 }
 
-fn init_bulidin_func(program: &mut Program, scope: &mut Scope) {
-    let getint = program.new_func(FunctionData::new_decl(
+fn init_bulidin_func(builder: &mut IrBuilder, scope: &mut Scope) {
+    let getint = builder.new_func(FunctionData::new_decl(
         "@getint".to_string(),
         vec![],
         Type::get_i32(),
     ));
     scope.global.function.insert("getint", getint);
 
-    let getch = program.new_func(FunctionData::new_decl(
+    let getch = builder.new_func(FunctionData::new_decl(
         "@getch".to_string(),
         vec![],
         Type::get_i32(),
     ));
     scope.global.function.insert("getch", getch);
 
-    let getarray = program.new_func(FunctionData::new_decl(
-        "@getarray".to_string(),
-        vec![Type::get_pointer(Type::get_i32())],
-        Type::get_i32(),
-    ));
-    scope.global.function.insert("getarray", getarray);
-
-    let getarray = program.new_func(FunctionData::new_decl(
+    let getarray = builder.new_func(FunctionData::new_decl(
         "@getarray".to_string(),
         vec![Type::get_pointer(Type::get_i32())],
         Type::get_i32(),
     ));
     scope.global.function.insert("getarray", getarray);
 
-    let putint = program.new_func(FunctionData::new_decl(
+    let putint = builder.new_func(FunctionData::new_decl(
         "@putint".to_string(),
         vec![Type::get_i32()],
         Type::get_unit(),
     ));
     scope.global.function.insert("putint", putint);
 
-    let putch = program.new_func(FunctionData::new_decl(
+    let putch = builder.new_func(FunctionData::new_decl(
         "@putch".to_string(),
         vec![Type::get_i32()],
         Type::get_unit(),
     ));
     scope.global.function.insert("putch", putch);
 
-    let putarray = program.new_func(FunctionData::new_decl(
+    let putarray = builder.new_func(FunctionData::new_decl(
         "@putarray".to_string(),
         vec![Type::get_i32(),Type::get_pointer(Type::get_i32())],
         Type::get_unit(),
     ));
     scope.global.function.insert("putarray", putarray);
 
-    let starttime = program.new_func(FunctionData::new_decl(
+    let starttime = builder.new_func(FunctionData::new_decl(
         "@starttime".to_string(),
         vec![],
         Type::get_unit(),
     ));
     scope.global.function.insert("starttime", starttime);
 
-    let stoptime = program.new_func(FunctionData::new_decl(
+    let stoptime = builder.new_func(FunctionData::new_decl(
         "@stoptime".to_string(),
         vec![],
         Type::get_unit(),
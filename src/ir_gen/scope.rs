@@ -1,15 +1,12 @@
-use super::{Error, Result};
 use crate::ir_gen::gen::SymbolValue;
-use crate::ir_gen::Error::NoInLoop;
-use koopa::ir::{BasicBlock, Function};
+use koopa::ir::{BasicBlock, Function, Value};
 use std::collections::HashMap;
 
 pub struct Scope<'ast> {
-    pub function: Option<Function>,
     pub global: Global<'ast>,
     loop_stack: Vec<LoopBlock>,
     symbol_tables: Vec<HashMap<&'ast str, SymbolValue>>,
-    curr_bb: Option<BasicBlock>,
+    const_arrays: HashMap<Value, ConstArray>,
 }
 
 #[derive(Clone)]
@@ -27,6 +24,28 @@ impl<'ast> Global<'ast> {
     }
 }
 
+/// compile time values of a const array, flattened in row-major order
+pub struct ConstArray {
+    pub dims: Vec<usize>,
+    pub values: Vec<i32>,
+}
+
+impl ConstArray {
+    pub fn get(&self, indices: &[i32]) -> Option<i32> {
+        if indices.len() != self.dims.len() {
+            return None;
+        }
+        let mut pos = 0;
+        for (&index, &dim) in indices.iter().zip(&self.dims) {
+            if index < 0 || index as usize >= dim {
+                return None;
+            }
+            pos = pos * dim + index as usize;
+        }
+        Some(self.values[pos])
+    }
+}
+
 #[derive(Copy, Clone)]
 pub struct LoopBlock {
     pub entry: BasicBlock,
@@ -35,78 +54,53 @@ pub struct LoopBlock {
 
 impl<'ast> Scope<'ast> {
     pub(crate) fn new(
-        function: Option<Function>,
         global: Global<'ast>,
         symbol_tables: Vec<HashMap<&'ast str, SymbolValue>>,
     ) -> Self {
         Self {
-            function,
             global,
             loop_stack: vec![],
             symbol_tables,
-            curr_bb: None,
+            const_arrays: HashMap::new(),
         }
     }
 
     pub fn in_global_scope(&self) -> bool {
-        self.function.is_none()
+        self.symbol_tables.is_empty()
     }
 
     pub fn reset_symbol_table(&mut self) {
         self.symbol_tables.clear();
-        self.function = None;
     }
 
-    pub fn is_curr_scope_exist(&self, k: &'ast str) -> bool {
-        match self.symbol_tables.last() {
-            Some(map) => {
-                map.contains_key(k)
-            }
-            None => self.global.decl.contains_key(k)
-        }
+    /// declares a local in the innermost scope, it hides any outer local,
+    /// global or function of the same name, redeclarations are already
+    /// rejected by the semantic pass
+    pub fn add(&mut self, k: &'ast str, v: SymbolValue) {
+        self.symbol_tables.last_mut().unwrap().insert(k, v);
     }
 
-    pub fn add(&mut self, k: &'ast str, v: SymbolValue) -> Result<()> {
-        let table = self.symbol_tables.last_mut().unwrap();
-        if let Some(_) = self.global.decl.get(k) {
-            return Err(Error::Redeclare(format!("name: {}", k)));
-        }
-        if let Some(already_exist) = table.insert(k, v) {
-            match already_exist {
-                SymbolValue::NeedLoad(_) => Ok(()),
-                SymbolValue::Value(_) | SymbolValue::GlobalConst(_) => Err(Error::ReassignConst(k.to_string())),
-            }
-        } else {
-            Ok(())
-        }
+    pub fn add_global_decl(&mut self, k: &'ast str, v: SymbolValue) {
+        self.global.decl.insert(k, v);
     }
 
-    pub fn add_global_decl(&mut self, k: &'ast str, v: SymbolValue) -> Result<()> {
-        for table in self.symbol_tables.iter() {
-            if let Some(_) = table.get(k) {
-                return Err(Error::Redeclare(format!("name: {}", k)));
-            }
-        }
-        if let Some(already_exist) = self.global.decl.insert(k, v) {
-            match already_exist {
-                SymbolValue::NeedLoad(_) => Ok(()),
-                SymbolValue::Value(_) | SymbolValue::GlobalConst(_) => Err(Error::ReassignConst(k.to_string())),
-            }
-        } else {
-            Ok(())
-        }
+    /// the innermost local, or else the global
+    pub fn get(&self, k: &str) -> SymbolValue {
+        self.symbol_tables
+            .iter()
+            .rev()
+            .find_map(|symbol| symbol.get(k))
+            .or_else(|| self.global.decl.get(k))
+            .copied()
+            .unwrap_or_else(|| unreachable!("undefined: {}", k))
     }
 
-    pub fn get(&self, k: &'ast str) -> Result<SymbolValue> {
-        for symbol in self.symbol_tables.iter().rev() {
-            if let Some(v) = symbol.get(k) {
-                return Ok(*v);
-            };
-        }
-        if let Some(v) = self.global.decl.get(k) {
-            return Ok(*v);
-        };
-        Err(Error::Undefined(format!("not found : {:?}", k)))
+    pub fn add_const_array(&mut self, ptr: Value, array: ConstArray) {
+        self.const_arrays.insert(ptr, array);
+    }
+
+    pub fn get_const_array(&self, ptr: Value) -> Option<&ConstArray> {
+        self.const_arrays.get(&ptr)
     }
 
     /// { // enter scope    
@@ -119,14 +113,6 @@ impl<'ast> Scope<'ast> {
         self.symbol_tables.pop();
     }
 
-    pub fn curr_bb(&self) -> BasicBlock {
-        self.curr_bb.unwrap()
-    }
-
-    pub fn set_bb(&mut self, bb: BasicBlock) {
-        self.curr_bb = Some(bb);
-    }
-
     pub fn enter_loop(&mut self, entry: BasicBlock, exit: BasicBlock) {
         self.loop_stack.push(LoopBlock { entry, exit })
     }
@@ -134,11 +120,9 @@ impl<'ast> Scope<'ast> {
     pub fn exit_loop(&mut self) {
         self.loop_stack.pop();
     }
-    pub fn get_loop_block(&mut self) -> Result<LoopBlock> {
-        if let Some(bbs) = self.loop_stack.last() {
-            Ok(bbs.clone())
-        } else {
-            Err(NoInLoop)
-        }
+    /// the innermost loop, `break` and `continue` are always in one after
+    /// the semantic pass
+    pub fn get_loop_block(&mut self) -> LoopBlock {
+        *self.loop_stack.last().unwrap()
     }
 }
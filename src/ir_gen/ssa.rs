@@ -0,0 +1,220 @@
+use std::collections::{HashMap, HashSet};
+
+use koopa::ir::builder_traits::ValueBuilder;
+use koopa::ir::{BasicBlock, FunctionData, TypeKind, Value, ValueKind};
+
+use crate::analysis::rewrite::{new_block_param, replace_uses, set_param_index};
+
+/// SSA construction on the fly, after Braun et al. "Simple and Efficient
+/// Construction of Static Single Assignment Form"
+///
+/// scalar locals are variables instead of memory: a store defines the
+/// variable in the current block and a load looks up the definition that
+/// reaches it, blocks where definitions merge get a parameter (the phi)
+#[derive(Default)]
+pub struct Ssa {
+    // the allocs standing for variables, they are never inserted
+    vars: HashSet<Value>,
+    // value of a variable at the end of a block
+    defs: HashMap<(Value, BasicBlock), Value>,
+    preds: HashMap<BasicBlock, Vec<BasicBlock>>,
+    // blocks whose predecessors are all known
+    sealed: HashSet<BasicBlock>,
+    // params of blocks not sealed yet, with their variable
+    incomplete: HashMap<BasicBlock, Vec<(Value, Value)>>,
+    // the argument each predecessor passes to a param, jumps get them once
+    // the function is done, since params may still be removed until then
+    args: HashMap<Value, Vec<(BasicBlock, Value)>>,
+    param_bb: HashMap<Value, BasicBlock>,
+    // params of each block, they join the block once the function is done,
+    // koopa refuses to build a plain jump to a block with params
+    params: HashMap<BasicBlock, Vec<Value>>,
+    // trivial params which are removed, and the value they stand for
+    replaced: HashMap<Value, Value>,
+    // params created since `take_new_params`, with their variable
+    new_params: Vec<(Value, Value)>,
+}
+
+impl Ssa {
+    pub fn add_var(&mut self, var: Value) {
+        self.vars.insert(var);
+    }
+
+    pub fn is_var(&self, value: Value) -> bool {
+        self.vars.contains(&value)
+    }
+
+    pub fn add_pred(&mut self, bb: BasicBlock, pred: BasicBlock) {
+        assert!(!self.sealed.contains(&bb), "jump to a sealed block");
+        let preds = self.preds.entry(bb).or_default();
+        if !preds.contains(&pred) {
+            preds.push(pred);
+        }
+    }
+
+    pub fn write(&mut self, var: Value, bb: BasicBlock, value: Value) {
+        self.defs.insert((var, bb), value);
+    }
+
+    /// the value of `var` at the end of `bb`, so far
+    pub fn read(&mut self, func: &mut FunctionData, var: Value, bb: BasicBlock) -> Value {
+        let value = match self.defs.get(&(var, bb)) {
+            Some(&value) => value,
+            None => self.read_recursive(func, var, bb),
+        };
+        self.resolve(value)
+    }
+
+    fn read_recursive(&mut self, func: &mut FunctionData, var: Value, bb: BasicBlock) -> Value {
+        let value = if !self.sealed.contains(&bb) {
+            let param = self.add_param(func, var, bb);
+            self.incomplete.entry(bb).or_default().push((var, param));
+            param
+        } else {
+            match self.preds.get(&bb).map(Vec::as_slice) {
+                // read before any store, C leaves it undefined
+                None | Some([]) => func.dfg_mut().new_value().integer(0),
+                Some(&[pred]) => self.read(func, var, pred),
+                Some(_) => {
+                    let param = self.add_param(func, var, bb);
+                    // a loop may lead back here while looking for the arguments
+                    self.write(var, bb, param);
+                    self.add_args(func, var, param)
+                }
+            }
+        };
+        self.write(var, bb, value);
+        value
+    }
+
+    /// no more predecessors of `bb` will be added
+    pub fn seal(&mut self, func: &mut FunctionData, bb: BasicBlock) {
+        if !self.sealed.insert(bb) {
+            return;
+        }
+        for (var, param) in self.incomplete.remove(&bb).unwrap_or_default() {
+            self.add_args(func, var, param);
+        }
+    }
+
+    fn resolve(&self, mut value: Value) -> Value {
+        while let Some(&v) = self.replaced.get(&value) {
+            value = v;
+        }
+        value
+    }
+
+    fn add_param(&mut self, func: &mut FunctionData, var: Value, bb: BasicBlock) -> Value {
+        let ty = match func.dfg().value(var).ty().kind() {
+            TypeKind::Pointer(base) => base.clone(),
+            _ => unreachable!("a variable is an alloc"),
+        };
+        // the index is only known once the function is done
+        let param = new_block_param(func.dfg_mut(), ty, 0);
+        self.param_bb.insert(param, bb);
+        self.params.entry(bb).or_default().push(param);
+        self.new_params.push((param, var));
+        param
+    }
+
+    fn add_args(&mut self, func: &mut FunctionData, var: Value, param: Value) -> Value {
+        let bb = self.param_bb[&param];
+        let mut args = vec![];
+        for pred in self.preds.get(&bb).cloned().unwrap_or_default() {
+            args.push((pred, self.read(func, var, pred)));
+        }
+        self.args.insert(param, args);
+        self.remove_trivial(func, param)
+    }
+
+    /// a param which only ever gets one value besides itself is that value
+    fn remove_trivial(&mut self, func: &mut FunctionData, param: Value) -> Value {
+        let mut same = None;
+        for &(_, arg) in &self.args[&param] {
+            let arg = self.resolve(arg);
+            if Some(arg) == same || arg == param {
+                continue;
+            }
+            if same.is_some() {
+                return param;
+            }
+            same = Some(arg);
+        }
+        // unreachable, or in a loop never entered with a value
+        let same = match same {
+            Some(same) => same,
+            None => func.dfg_mut().new_value().integer(0),
+        };
+
+        self.replaced.insert(param, same);
+        replace_uses(func.dfg_mut(), &HashMap::from([(param, same)]));
+        let bb = self.param_bb.remove(&param).unwrap();
+        self.params.get_mut(&bb).unwrap().retain(|&p| p != param);
+        self.args.remove(&param);
+        func.dfg_mut().remove_value(param);
+
+        // params that got this one may be trivial now
+        let param_users: Vec<_> = self
+            .args
+            .iter()
+            .filter(|(_, args)| args.iter().any(|&(_, arg)| arg == param))
+            .map(|(&p, _)| p)
+            .collect();
+        for user in param_users {
+            if self.args.contains_key(&user) {
+                self.remove_trivial(func, user);
+            }
+        }
+        same
+    }
+
+    /// params which still need a name, with the variable they belong to
+    pub fn take_new_params(&mut self) -> Vec<(Value, Value)> {
+        std::mem::take(&mut self.new_params)
+    }
+
+    /// adds the params to their blocks and passes their arguments along the
+    /// jumps, then drops the variables, the function must be complete
+    pub fn finish(&mut self, func: &mut FunctionData) {
+        let bbs: Vec<_> = func.layout().bbs().keys().copied().collect();
+        for &bb in &bbs {
+            assert!(self.sealed.contains(&bb), "block is never sealed");
+            let params = self.params.remove(&bb).unwrap_or_default();
+            if params.is_empty() {
+                continue;
+            }
+            for (index, &param) in params.iter().enumerate() {
+                set_param_index(func.dfg_mut(), param, index);
+            }
+            *func.dfg_mut().bb_mut(bb).params_mut() = params.clone();
+            for &pred in &self.preds[&bb] {
+                let args: Vec<_> = params
+                    .iter()
+                    .map(|param| {
+                        let &(_, arg) = self.args[param].iter().find(|(p, _)| *p == pred).unwrap();
+                        self.resolve(arg)
+                    })
+                    .collect();
+                let node = func.layout().bbs().node(&pred).unwrap();
+                let jump = *node.insts().back_key().unwrap();
+                let mut data = func.dfg().value(jump).clone();
+                match data.kind_mut() {
+                    ValueKind::Jump(jump) => *jump.args_mut() = args,
+                    ValueKind::Branch(br) => {
+                        if br.true_bb() == bb {
+                            *br.true_args_mut() = args.clone();
+                        }
+                        if br.false_bb() == bb {
+                            *br.false_args_mut() = args;
+                        }
+                    }
+                    _ => unreachable!("a predecessor ends with a jump"),
+                }
+                func.dfg_mut().replace_value_with(jump).raw(data);
+            }
+        }
+        for var in self.vars.drain() {
+            func.dfg_mut().remove_value(var);
+        }
+    }
+}
@@ -3,17 +3,22 @@ use std::fmt::{self};
 
 use std::fs::{read_to_string, File};
 
+use diagnostic::{Diagnostics, Source};
+use semantic::{Warning, WarningOptions};
 use koopa::back::KoopaGenerator;
-use lalrpop_util::lalrpop_mod;
+use lalrpop_util::{lalrpop_mod, ParseError};
 use std::process::exit;
+use std::io::Write;
 use std::{io, vec};
 
 mod ast;
+mod diagnostic;
 mod ir_gen;
 mod riscv_gen;
+mod semantic;
 mod analysis;
 
-lalrpop_mod!(sysy);
+lalrpop_mod!(#[allow(clippy::all)] sysy);
 fn main() {
     let mut args = args();
     args.next();
@@ -23,7 +28,7 @@ fn main() {
     let output = args.next().unwrap();
 
     let mut ss = vec![];
-    while let Some(arg) = args.next() {
+    for arg in args {
         ss.push(arg);
     }
     if let Err(err) = try_main(Args {
@@ -37,6 +42,36 @@ fn main() {
     }
 }
 
+/// returns the (partial) AST and reports all syntax errors, the AST is missing
+/// only if the parser can't recover
+fn parse(source: &Source, diags: &mut Diagnostics) -> Option<ast::CompUnit> {
+    let mut recovered = vec![];
+    let result = sysy::CompUnitParser::new().parse(&mut recovered, source.text);
+
+    let mut pending: Vec<_> = recovered.iter().map(|e| (&e.error, e.dropped_tokens.last())).collect();
+    if let Err(e) = &result {
+        pending.push((e, None));
+    }
+    // like bison, errors right after a recovery are usually caused by it, so
+    // they are dropped until 3 tokens are shifted again
+    let mut resumed_at = None;
+    for (error, last_dropped) in pending {
+        let (span, msg) = source.parse_error(error);
+        // errors of grammar actions don't disturb the parser
+        if let ParseError::User { .. } = error {
+            diags.error(span, msg);
+            continue;
+        }
+        let cascaded = matches!(resumed_at, Some(at) if source.count_tokens(at, span.start) < 3);
+        if !cascaded {
+            diags.error(span, msg);
+        }
+        // parsing goes on from the first token not dropped
+        resumed_at = Some(last_dropped.map_or(span.start, |&(_, _, end)| end));
+    }
+    result.ok()
+}
+
 #[derive(Debug)]
 struct Args {
     mode: String,
@@ -46,10 +81,35 @@ struct Args {
 }
 
 fn try_main(args: Args) -> Result<(), Error> {
-    let input = read_to_string(args.input).map_err(Error::File)?;
-    let ast = sysy::CompUnitParser::new()
-        .parse(&input)
-        .map_err(|_| Error::Parse)?;
+    let opt_options = analysis::pass::Options::from_args(&args.args).map_err(Error::Pass)?;
+    let warning_options = WarningOptions::from_args(&args.args).map_err(Error::Warning)?;
+    let input = read_to_string(&args.input).map_err(Error::File)?;
+    let source = Source::new(&args.input, &input);
+    let mut diags = Diagnostics::default();
+    let ast = parse(&source, &mut diags);
+    // a partial AST would only bring more confusing errors
+    if let Some(ast) = ast.as_ref().filter(|_| !diags.has_errors()) {
+        semantic::check_program(ast, &warning_options, &mut diags);
+    }
+    let ast = match ast {
+        Some(ast) if !diags.has_errors() => ast,
+        _ => return Err(Error::Compile(diags.render(&source))),
+    };
+    let options = ir_gen::Options::from_args(&args.args);
+    let (mut program, reads) = ir_gen::generate_program(&ast, &options);
+    // the passes would take the loads away
+    if warning_options.is_enabled(Warning::Uninitialized) {
+        for (span, msg) in analysis::uninit::check(&program, &reads) {
+            warning_options.report(Warning::Uninitialized, span, msg, &mut diags);
+        }
+        if diags.has_errors() {
+            return Err(Error::Compile(diags.render(&source)));
+        }
+    }
+    opt_options.pass_manager().run(&mut program);
+    if !diags.is_empty() {
+        eprintln!("{}", diags.render(&source));
+    }
 
     println!(
         "\ninput source code:\n======================\n{}======================\n",
@@ -58,7 +118,12 @@ fn try_main(args: Args) -> Result<(), Error> {
     match args.mode.as_str() {
         "-koopa" => {
             let output_file = File::create(args.output).map_err(Error::File)?;
-            let program = ir_gen::generate_program(&ast).map_err(Error::KoopaGen)?;
+            if opt_options.dump_live {
+                let annotated = analysis::live_variable::annotate(&mut program);
+                return (&output_file)
+                    .write_all(annotated.as_bytes())
+                    .map_err(Error::File);
+            }
             KoopaGenerator::new(output_file)
                 .generate_on(&program)
                 .unwrap();
@@ -66,8 +131,7 @@ fn try_main(args: Args) -> Result<(), Error> {
         }
         "-riscv" => {
             let output_file = File::create(args.output).map_err(Error::File)?;
-            let koopa = ir_gen::generate_program(&ast).map_err(Error::KoopaGen)?;
-            let _ = riscv_gen::generate_riscv(koopa, args.args)
+            let _ = riscv_gen::generate_riscv(program, opt_options.peephole)
                 .map_err(Error::RiscvGen)?
                 .generate_on(output_file);
             Ok(())
@@ -80,26 +144,33 @@ fn try_main(args: Args) -> Result<(), Error> {
 
 enum Error {
     File(io::Error),
-    Parse,
-    KoopaGen(ir_gen::Error),
+    // all diagnostics, rendered with the source locations
+    Compile(String),
     RiscvGen(riscv_gen::Error),
+    Pass(analysis::pass::Error),
+    Warning(semantic::UnknownWarning),
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Self::Parse => write!(f, "error occurred while parsing"),
             Self::File(err) => write!(f, "invalid input SysY file: {}", err),
-            Self::KoopaGen(err) => write!(f, "koopa gen error: {:?}", err),
+            Self::Compile(diags) => write!(f, "{}", diags),
             Self::RiscvGen(err) => write!(f, "gen isa error: {:?}", err),
+            Self::Pass(err) => write!(f, "{}", err),
+            Self::Warning(err) => write!(f, "{}", err),
         }
     }
 }
 
 #[cfg(test)]
 mod test {
+    /// the output is parsed back unless `$reparse` is false
     macro_rules! test_koopa {
         ($file_name: ident) => {
+            test_koopa!($file_name, true);
+        };
+        ($file_name: ident, $reparse: expr) => {
             #[test]
             fn $file_name() {
                 let file_name = stringify!($file_name);
@@ -123,6 +194,14 @@ mod test {
                 if let Err(e) = try_main(args) {
                     panic!("{}", e.to_string());
                 }
+                // the output must be well-formed koopa, every block terminated
+                // once and every symbol defined once
+                if $reparse {
+                    let koopa = fs::read_to_string(&old_koopa).unwrap();
+                    if let Err(e) = koopa::front::Driver::from(koopa).generate_program() {
+                        panic!("invalid koopa of {}: {:?}", file_name, e);
+                    }
+                }
 
                 let file_name_og = format!("{}_og", file_name);
                 let args = Args {
@@ -163,6 +242,12 @@ mod test {
                 if let Err(e) = try_main(args) {
                     panic!("{}", e.to_string());
                 }
+                // the assembler rejects a label defined twice
+                let asm = fs::read_to_string(&old_riscv).unwrap();
+                let mut labels = HashSet::new();
+                for label in asm.lines().filter(|line| line.ends_with(':')) {
+                    assert!(labels.insert(label), "duplicate label {} in {}", label, file_name);
+                }
 
                 let file_name_og = format!("{}_og", file_name);
                 let args = Args {
@@ -179,6 +264,33 @@ mod test {
         };
     }
 
+    /// compiling the file must fail with exactly `$expected`
+    macro_rules! test_error {
+        ($file_name: ident, $expected: expr) => {
+            test_error!($file_name, [], $expected);
+        };
+        ($file_name: ident, [$($arg: expr),*], $expected: expr) => {
+            #[test]
+            fn $file_name() {
+                let file_name = stringify!($file_name);
+                if !PathBuf::from("./tests/output").exists() {
+                    fs::create_dir_all("./tests/output").unwrap();
+                }
+
+                let args = Args {
+                    mode: "-koopa".to_string(),
+                    input: format!("{}{}{}", "./tests/input/", file_name, ".c"),
+                    output: format!("{}{}{}", "./tests/output/", file_name, ".koopa"),
+                    args: vec![$($arg.to_string()),*],
+                };
+                match try_main(args) {
+                    Ok(_) => panic!("{} should not compile", file_name),
+                    Err(e) => assert_eq!(e.to_string(), $expected),
+                }
+            }
+        };
+    }
+
     mod koopa {
         use crate::{try_main, Args};
         use std::{
@@ -213,10 +325,125 @@ mod test {
         test_koopa!(global_var1);
         test_koopa!(buildin);
         test_koopa!(peephole);
+        test_koopa!(array1);
+        test_koopa!(global_array1);
+        test_koopa!(array_init);
+        test_koopa!(array_param);
+        test_koopa!(comment_literal);
+        test_koopa!(warning_flags);
+        test_koopa!(const_wrap);
+        // the koopa parser can't resolve calls to functions defined later
+        test_koopa!(func_decl, false);
+        test_koopa!(scope_global);
+        test_koopa!(scope_function);
+        test_koopa!(scope_param);
+        test_koopa!(unique_names);
+        test_koopa!(labels);
+        test_koopa!(terminator);
+        test_koopa!(cond_branch);
+        test_koopa!(ssa_loop);
+        test_koopa!(const_lazy);
+
+        /// `&&` and `||` in a condition branch directly, without a result slot
+        #[test]
+        fn cond_branch_no_slot() {
+            fs::create_dir_all("./tests/output").unwrap();
+            let output = "./tests/output/cond_branch_no_slot.koopa".to_string();
+            let args = Args {
+                mode: "-koopa".to_string(),
+                input: "./tests/input/cond_branch.c".to_string(),
+                output: output.clone(),
+                args: vec![],
+            };
+            if let Err(e) = try_main(args) {
+                panic!("{}", e);
+            }
+            let koopa = fs::read_to_string(&output).unwrap();
+            assert!(!koopa.contains("%logic_result"), "{}", koopa);
+        }
+
+        /// compiles ssa_loop.c with `arg`, scalar locals must never touch
+        /// memory, merges are block params
+        fn assert_no_memory(output: &str, arg: &str) {
+            fs::create_dir_all("./tests/output").unwrap();
+            let args = Args {
+                mode: "-koopa".to_string(),
+                input: "./tests/input/ssa_loop.c".to_string(),
+                output: output.to_string(),
+                args: vec![arg.to_string()],
+            };
+            if let Err(e) = try_main(args) {
+                panic!("{}", e);
+            }
+            let koopa = fs::read_to_string(output).unwrap();
+            for inst in ["alloc", "load", "store"] {
+                assert!(!koopa.contains(inst), "{} in {}", inst, koopa);
+            }
+            assert!(koopa.contains("%while_cond(%"), "{}", koopa);
+            if let Err(e) = koopa::front::Driver::from(koopa).generate_program() {
+                panic!("invalid koopa: {:?}", e);
+            }
+        }
+
+        #[test]
+        fn ssa_loop_no_memory() {
+            assert_no_memory("./tests/output/ssa_loop_no_memory.koopa", "-ssa");
+        }
+
+        #[test]
+        fn ssa_loop_mem2reg() {
+            assert_no_memory("./tests/output/ssa_loop_mem2reg.koopa", "--passes=mem2reg");
+        }
+
+        /// -koopa prints the IR after the passes of the level
+        #[test]
+        fn opt_levels() {
+            fs::create_dir_all("./tests/output").unwrap();
+            for level in ["-O0", "-O1", "-O2"] {
+                let output = format!("./tests/output/opt_levels{}.koopa", level);
+                let args = Args {
+                    mode: "-koopa".to_string(),
+                    input: "./tests/input/block.c".to_string(),
+                    output: output.clone(),
+                    args: vec![level.to_string()],
+                };
+                if let Err(e) = try_main(args) {
+                    panic!("{}", e);
+                }
+                let koopa = fs::read_to_string(&output).unwrap();
+                assert_eq!(koopa.contains("alloc"), level == "-O0", "{}", koopa);
+                // 2 + 2 is folded
+                assert_eq!(koopa.contains("ret 4"), level == "-O2", "{}", koopa);
+            }
+        }
+
+        /// --dump-live puts the live values in comments, the IR stays valid
+        #[test]
+        fn dump_live() {
+            fs::create_dir_all("./tests/output").unwrap();
+            let output = "./tests/output/dump_live.koopa";
+            let args = Args {
+                mode: "-koopa".to_string(),
+                input: "./tests/input/ssa_loop.c".to_string(),
+                output: output.to_string(),
+                args: vec!["-O1".to_string(), "--dump-live".to_string()],
+            };
+            if let Err(e) = try_main(args) {
+                panic!("{}", e);
+            }
+            let koopa = fs::read_to_string(output).unwrap();
+            assert!(koopa.contains("%entry: // live in: {@a, @b}"), "{}", koopa);
+            assert!(koopa.contains("%while_body: // live in: {%a, %b}"), "{}", koopa);
+            assert!(koopa.contains("ret %a // live: {}"), "{}", koopa);
+            if let Err(e) = koopa::front::Driver::from(koopa).generate_program() {
+                panic!("invalid koopa: {:?}", e);
+            }
+        }
     }
     mod riscv {
         use crate::{try_main, Args};
         use std::{
+            collections::HashSet,
             fs::{self},
             path::PathBuf,
         };
@@ -248,5 +475,243 @@ mod test {
         test_riscv!(global_var1);
         test_riscv!(buildin);
         test_riscv!(peephole);
+        test_riscv!(array1);
+        test_riscv!(global_array1);
+        test_riscv!(array_init);
+        test_riscv!(array_param);
+        test_riscv!(comment_literal);
+        test_riscv!(const_wrap);
+        test_riscv!(func_decl);
+        test_riscv!(scope_global);
+        test_riscv!(scope_function);
+        test_riscv!(scope_param);
+        test_riscv!(unique_names);
+        test_riscv!(labels);
+        test_riscv!(terminator);
+        test_riscv!(cond_branch);
+        test_riscv!(ssa_loop);
+        test_riscv!(regalloc);
+
+        /// block arguments are passed on jumps, through edge labels on branches
+        #[test]
+        fn ssa_loop_block_args() {
+            fs::create_dir_all("./tests/output").unwrap();
+            let output = "./tests/output/ssa_loop_block_args.riscv".to_string();
+            let args = Args {
+                mode: "-riscv".to_string(),
+                input: "./tests/input/ssa_loop.c".to_string(),
+                output: output.clone(),
+                args: vec!["-ssa".to_string(), "-p".to_string()],
+            };
+            if let Err(e) = try_main(args) {
+                panic!("{}", e);
+            }
+            let asm = fs::read_to_string(&output).unwrap();
+            let mut labels = HashSet::new();
+            for label in asm.lines().filter(|line| line.ends_with(':')) {
+                assert!(labels.insert(label), "duplicate label {}", label);
+            }
+            assert!(labels.contains(".Lmain.lor_end.1:"), "{}", asm);
+        }
+
+        fn compile_o2(name: &str) -> String {
+            fs::create_dir_all("./tests/output").unwrap();
+            let output = format!("./tests/output/{}_o2.riscv", name);
+            let args = Args {
+                mode: "-riscv".to_string(),
+                input: format!("./tests/input/{}.c", name),
+                output: output.clone(),
+                args: vec!["-O2".to_string()],
+            };
+            if let Err(e) = try_main(args) {
+                panic!("{}", e);
+            }
+            fs::read_to_string(&output).unwrap()
+        }
+
+        /// values live in registers, a leaf function never touches the stack
+        #[test]
+        fn regalloc_leaf() {
+            let asm = compile_o2("ssa_loop");
+            let gcd: Vec<_> = asm
+                .lines()
+                .skip_while(|line| *line != "gcd:")
+                .take_while(|line| *line != "  ret")
+                .collect();
+            assert!(!gcd.is_empty(), "{}", asm);
+            assert!(gcd.iter().all(|line| !line.contains("sp")), "{}", asm);
+        }
+
+        /// the callee-saved registers a function uses are saved and restored
+        #[test]
+        fn regalloc_saved() {
+            let asm = compile_o2("regalloc");
+            let main: Vec<_> = asm.lines().skip_while(|line| *line != "main:").collect();
+            let regs = |op: &str| -> HashSet<_> {
+                main.iter()
+                    .filter_map(|line| line.strip_prefix(op))
+                    .filter_map(|line| line.split(',').next())
+                    .filter(|reg| reg.starts_with('s') && *reg != "sp")
+                    .collect()
+            };
+            let saved = regs("  sw ");
+            assert_eq!(saved.len(), 11, "{}", asm);
+            assert_eq!(saved, regs("  lw "), "{}", asm);
+        }
+    }
+    mod error {
+        use crate::diagnostic::{Diagnostics, Source};
+        use crate::{parse, try_main, Args};
+        use std::{
+            fs::{self},
+            path::PathBuf,
+        };
+
+        #[test]
+        fn error_parse_recover() {
+            let name = "./tests/input/error_parse_recover.c";
+            let text = fs::read_to_string(name).unwrap();
+            let source = Source::new(name, &text);
+            let mut diags = Diagnostics::default();
+            let ast = parse(&source, &mut diags);
+            assert!(ast.is_some(), "partial AST should be built");
+            let rendered = diags.render(&source);
+            let lines: Vec<_> = rendered
+                .lines()
+                .filter(|line| line.contains(": error: "))
+                .map(|line| line.split(": ").next().unwrap())
+                .collect();
+            assert_eq!(
+                lines,
+                [
+                    "./tests/input/error_parse_recover.c:1:9",
+                    "./tests/input/error_parse_recover.c:4:14",
+                    "./tests/input/error_parse_recover.c:5:9",
+                    "./tests/input/error_parse_recover.c:6:13",
+                ]
+            );
+        }
+
+        test_error!(
+            error_undefined,
+            "./tests/input/error_undefined.c:3:14: error: undefined: b\n 3 |   return a + b;\n   |              ^\n1 error generated."
+        );
+        test_error!(
+            error_break,
+            "./tests/input/error_break.c:4:2: error: break/continue statement not within a loop\n 4 | \tbreak;\n   | \t^^^^^^\n1 error generated."
+        );
+        test_error!(
+            error_const_assign,
+            "./tests/input/error_const_assign.c:3:3: error: assignment to const: N\n 3 |   N = 4;\n   |   ^\n1 error generated."
+        );
+        test_error!(
+            error_redeclare,
+            "./tests/input/error_redeclare.c:2:5: error: redeclaration of a\n 2 | int a[2] = {1, 2};\n   |     ^^^^^^^^^^^^^\n1 error generated."
+        );
+        test_error!(
+            error_literal,
+            "./tests/input/error_literal.c:2:11: error: integer literal is out of range\n 2 |   int a = 2147483648;\n   |           ^^^^^^^^^^\n./tests/input/error_literal.c:3:12: error: integer literal is out of range\n 3 |   int b = -6442450944;\n   |            ^^^^^^^^^^\n./tests/input/error_literal.c:4:18: error: integer literal is out of range\n 4 |   return a + b + 99999999999999999999999 + -2147483648;\n   |                  ^^^^^^^^^^^^^^^^^^^^^^^\n3 errors generated."
+        );
+        test_error!(
+            error_void_decl,
+            "./tests/input/error_void_decl.c:1:8: error: variable or parameter declared void\n 1 | void f(void x) {\n   |        ^^^^\n./tests/input/error_void_decl.c:5:3: error: variable or parameter declared void\n 5 |   void y;\n   |   ^^^^\n./tests/input/error_void_decl.c:6:9: error: variable or parameter declared void\n 6 |   const void z = 1;\n   |         ^^^^\n3 errors generated."
+        );
+        test_error!(
+            error_void_value,
+            "./tests/input/error_void_value.c:4:11: error: void value not ignored as it ought to be\n 4 |   int a = f() + 1;\n   |           ^^^\n1 error generated."
+        );
+        test_error!(
+            error_arg_count,
+            "./tests/input/error_arg_count.c:6:10: error: add expects 2 argument(s), found 1\n 6 |   return add(1);\n   |          ^^^^^^\n1 error generated."
+        );
+        test_error!(
+            error_arg_type,
+            "./tests/input/error_arg_type.c:7:14: error: mismatched types: expected int[][3], found int[2][4]\n 7 |   return sum(m);\n   |              ^\n1 error generated."
+        );
+        test_error!(
+            error_return,
+            "./tests/input/error_return.c:3:5: error: non-void function f should return a value\n 3 |     return;\n   |     ^^^^^^^\n1 error generated."
+        );
+        test_error!(
+            error_no_main,
+            "./tests/input/error_no_main.c:1:1: error: no `int main()` function is defined\n 1 | int f() {\n   | ^\n1 error generated."
+        );
+        test_error!(
+            error_not_const,
+            "./tests/input/error_not_const.c:2:7: error: expression is not a compile time constant\n 2 | int a[n];\n   |       ^\n1 error generated."
+        );
+        test_error!(
+            error_many,
+            "./tests/input/error_many.c:6:11: error: undefined: y\n 6 |   int x = y;\n   |           ^\n./tests/input/error_many.c:7:7: error: f expects 1 argument(s), found 2\n 7 |   x = f(1, 2);\n   |       ^^^^^^^\n./tests/input/error_many.c:8:10: error: undefined: z\n 8 |   while (z) {\n   |          ^\n./tests/input/error_many.c:9:13: error: undefined: w\n 9 |     x = x + w;\n   |             ^\n./tests/input/error_many.c:12:3: error: assignment to const: c\n 12 |   c = x;\n    |   ^\n./tests/input/error_many.c:13:10: error: undefined: v\n 13 |   return v;\n    |          ^\n6 errors generated."
+        );
+        test_error!(
+            error_parse_token,
+            "./tests/input/error_parse_token.c:1:11: error: unexpected `{`, expected one of \")\", \"int\", \"void\"\n 1 | int main( {\n   |           ^\n1 error generated."
+        );
+        test_error!(
+            error_parse_invalid,
+            "./tests/input/error_parse_invalid.c:2:12: error: invalid token `@`\n 2 |   return 1 @ 2;\n   |            ^\n1 error generated."
+        );
+        test_error!(
+            error_parse_eof,
+            "./tests/input/error_parse_eof.c:1:11: error: unexpected end of file, expected one of \";\", \"{\"\n 1 | int main()\n   |           ^\n1 error generated."
+        );
+        test_error!(
+            error_const_expr,
+            ["-Wall"],
+            "./tests/input/error_const_expr.c:2:7: error: call to f is not allowed in a constant expression\n 2 | int a[f()];\n   |       ^^^\n./tests/input/error_const_expr.c:3:7: error: division by zero in constant expression\n 3 | int b[1 / 0];\n   |       ^^^^^\n./tests/input/error_const_expr.c:4:20: error: division by zero in constant expression\n 4 | const int c = 1 && 2 % 0;\n   |                    ^^^^^\n./tests/input/error_const_expr.c:6:13: warning: unused variable `d` [-Wunused-variable]\n 6 |   const int d = 0 || f();\n   |             ^^^^^^^^^^^^\n./tests/input/error_const_expr.c:6:22: error: call to f is not allowed in a constant expression\n 6 |   const int d = 0 || f();\n   |                      ^^^\n4 errors and 1 warning generated."
+        );
+        test_error!(
+            error_div_zero,
+            "./tests/input/error_div_zero.c:2:15: error: division by zero in constant expression\n 2 | const int m = n % (n - 4);\n   |               ^^^^^^^^^^^\n1 error generated."
+        );
+        test_error!(
+            error_func_decl,
+            "./tests/input/error_func_decl.c:4:1: error: conflicting types for f\n 4 | int f(int a, int b) {\n   | ^^^^^^^^^^^^^^^^^^^^^\n./tests/input/error_func_decl.c:8:1: error: conflicting types for g\n 8 | int g() {\n   | ^^^^^^^^^\n./tests/input/error_func_decl.c:9:10: error: undefined: h\n 9 |   return h();\n   |          ^^^\n./tests/input/error_func_decl.c:13:10: error: f expects 1 argument(s), found 2\n 13 |   return f(1, 2);\n    |          ^^^^^^^\n4 errors generated."
+        );
+        test_error!(
+            error_scope_param,
+            "./tests/input/error_scope_param.c:2:7: error: redeclaration of a\n 2 |   int a = 1;\n   |       ^^^^^\n1 error generated."
+        );
+        test_error!(
+            error_scope_block,
+            ["-Wall"],
+            "./tests/input/error_scope_block.c:2:5: error: redeclaration of x\n 2 | int x;\n   |     ^\n./tests/input/error_scope_block.c:7:9: warning: unused variable `b` [-Wunused-variable]\n 7 |     int b = a;\n   |         ^^^^^\n./tests/input/error_scope_block.c:8:9: error: redeclaration of b\n 8 |     int b = 2;\n   |         ^^^^^\n./tests/input/error_scope_block.c:10:10: error: undefined: b\n 10 |   return b;\n    |          ^\n3 errors and 1 warning generated."
+        );
+        test_error!(
+            error_scope_call,
+            ["-Wall"],
+            "./tests/input/error_scope_call.c:6:7: warning: unused variable `f` [-Wunused-variable]\n 6 |   int f = 2;\n   |       ^^^^^\n./tests/input/error_scope_call.c:7:10: error: called object is not a function: f\n 7 |   return f();\n   |          ^^^\n1 error and 1 warning generated."
+        );
+        test_error!(
+            warning_werror,
+            ["-Werror", "-Wall", "-Wextra", "-Wshadow"],
+            "./tests/input/warning_werror.c:1:14: error: unused parameter `y` [-Werror=unused-parameter]\n 1 | int f(int x, int y) {\n   |              ^^^^^\n./tests/input/warning_werror.c:2:7: error: unused variable `unused` [-Werror=unused-variable]\n 2 |   int unused = 1;\n   |       ^^^^^^^^^^\n./tests/input/warning_werror.c:4:9: error: declaration of `x` shadows a previous declaration [-Werror=shadow]\n 4 |     int x = 2;\n   |         ^^^^^\n./tests/input/warning_werror.c:7:1: error: control reaches end of non-void function [-Werror=return-type]\n 7 | }\n   | ^\n./tests/input/warning_werror.c:11:3: error: loop condition is always true and the loop has no break [-Werror=infinite-loop]\n 11 |   while (1) {\n    |   ^^^^^^^^^^^\n./tests/input/warning_werror.c:14:3: error: code will never be executed [-Werror=unreachable-code]\n 14 |   return a;\n    |   ^^^^^^^^^\n6 errors generated."
+        );
+        test_error!(
+            error_unknown_pass,
+            ["-O2", "--passes=mem2reg,inline"],
+            "unknown pass: inline, expected one of mem2reg, constfold, dce"
+        );
+        test_error!(
+            warning_flags,
+            ["-Werror", "-Wall", "-Wno-unused-variable"],
+            "./tests/input/warning_flags.c:5:3: error: code will never be executed [-Werror=unreachable-code]\n 5 |   a = 3;\n   |   ^^^^^^\n1 error generated."
+        );
+        test_error!(
+            error_unknown_warning,
+            ["-Wall", "-Wfoo"],
+            "unknown warning option: -Wfoo"
+        );
+        test_error!(
+            warning_uninit,
+            ["-Werror", "-Wuninitialized"],
+            "./tests/input/warning_uninit.c:9:13: error: `x` is used uninitialized [-Werror=uninitialized]\n 9 |     z = z + x;\n   |             ^\n./tests/input/warning_uninit.c:12:14: error: `y` may be used uninitialized [-Werror=uninitialized]\n 12 |   return x + y + z;\n    |              ^\n2 errors generated."
+        );
+        test_error!(
+            warning_excess_init,
+            ["-Werror"],
+            "./tests/input/warning_excess_init.c:2:25: error: excess elements in scalar initializer [-Werror=excess-initializers]\n 2 |   int a[2][3] = {1, {2, 3}, 4};\n   |                         ^\n1 error generated."
+        );
     }
 }
@@ -2,7 +2,7 @@ use crate::riscv_gen::gen::AsmValue;
 use koopa::ir::{Function, Value};
 use std::collections::HashMap;
 
-use super::reg::{RegisterManager, Registers};
+use super::reg::RegisterManager;
 
 pub struct Context {
     pub function_table: HashMap<Function, String>,
@@ -11,6 +11,10 @@ pub struct Context {
     pub stack_used_size: usize,
     pub ra_pos: Option<usize>,
     pub symbol_table: HashMap<Value, AsmValue>,
+    // callee-saved registers in use and where they are saved
+    pub saved_regs: Vec<(String, usize)>,
+    // labels made for branch edges which pass arguments
+    pub edge_count: usize,
     pub register_manager: RegisterManager,
 }
 
@@ -22,6 +26,8 @@ impl Context {
             stack_used_size: 0,
             ra_pos: None,
             symbol_table: HashMap::new(),
+            saved_regs: vec![],
+            edge_count: 0,
             register_manager: RegisterManager::new(),
         }
     }
@@ -29,10 +35,15 @@ impl Context {
     pub fn clear(&mut self) {
         self.stack_size = 0;
         self.stack_used_size = 0;
-        self.symbol_table.clear();
+        self.ra_pos = None;
+        self.saved_regs.clear();
+        self.edge_count = 0;
+        // globals are shared by all functions
+        self.symbol_table.retain(|value, _| value.is_global());
     }
 
-    pub fn get_useful_space(&mut self, size: usize) -> String {
+    /// returns the offset from sp
+    pub fn get_useful_space(&mut self, size: usize) -> usize {
         let start_pos = self.stack_used_size;
         self.stack_used_size += size;
 
@@ -42,7 +53,7 @@ impl Context {
             self.stack_used_size,
             self.stack_size
         );
-        format!("{}(sp)", start_pos)
+        start_pos
     }
 
     pub fn get_symbol(&self, key: &Value) -> Option<&AsmValue> {
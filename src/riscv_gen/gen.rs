@@ -1,22 +1,22 @@
 use crate::riscv_gen::context::Context;
 use crate::riscv_gen::inst::Inst;
+use crate::riscv_gen::reg::{Location, RegisterManager};
 use koopa::front::ast::Error;
+use koopa::ir::entities::ValueData;
 use koopa::ir::{self, *};
 use std::cmp::max;
 use std::vec;
 use std::{fs::File, io::Write};
 
+/// `%then` of `@main` => `.Lmain.then`, `.L` keeps the label out of the symbol
+/// table, and `.` can't clash with identifiers like `_` could
 macro_rules! bb_name {
     ($func_data: expr, $bb: expr) => {
-        $func_data
-            .dfg()
-            .bb($bb)
-            .name()
-            .clone()
-            .unwrap()
-            .chars()
-            .skip(1)
-            .collect::<String>()
+        format!(
+            ".L{}.{}",
+            &$func_data.name()[1..],
+            &$func_data.dfg().bb($bb).name().as_ref().unwrap()[1..]
+        )
     };
 }
 
@@ -48,10 +48,11 @@ impl Program {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum AsmValue {
     Const(i32),
-    Value(String),
+    // offset from sp
+    Stack(usize),
     Register(String),
     GlobalVar(String),
 }
@@ -63,11 +64,17 @@ impl AsmValue {
                 program.push_inst(Inst::Li(reg.to_string(), *int));
                 reg.to_string()
             }
-            AsmValue::Value(pos) => {
-                program.push_inst(Inst::Lw(reg.to_string(), pos.to_string()));
+            AsmValue::Stack(offset) => {
+                let pos = stack_pos(program, *offset, reg);
+                program.push_inst(Inst::Lw(reg.to_string(), pos));
+                reg.to_string()
+            }
+            AsmValue::Register(r) => {
+                if r != reg {
+                    program.push_inst(Inst::Mv(reg.to_string(), r.to_string()));
+                }
                 reg.to_string()
             }
-            AsmValue::Register(r) => r.to_string(),
             AsmValue::GlobalVar(label) => {
                 program.push_inst(Inst::La(reg.to_string(), label.to_string()));
                 program.push_inst(Inst::Lw(reg.to_string(), format!("0({})", reg)));
@@ -77,6 +84,36 @@ impl AsmValue {
     }
 }
 
+/// lw/sw only take a 12 bits immediate [-2048, 2047],
+/// larger offsets go through `tmp`
+fn stack_pos(program: &mut Program, offset: usize, tmp: &str) -> String {
+    if offset < 2048 {
+        format!("{}(sp)", offset)
+    } else {
+        stack_addr(program, offset, tmp);
+        format!("0({})", tmp)
+    }
+}
+
+/// reg = sp + offset
+fn stack_addr(program: &mut Program, offset: usize, reg: &str) {
+    if offset < 2048 {
+        program.push_inst(Inst::Addi(reg.to_string(), "sp".to_string(), offset as i32));
+    } else {
+        program.push_inst(Inst::Li(reg.to_string(), offset as i32));
+        program.push_inst(Inst::Add(
+            reg.to_string(),
+            "sp".to_string(),
+            reg.to_string(),
+        ));
+    }
+}
+
+fn store_to_stack(program: &mut Program, reg: &str, offset: usize) {
+    let pos = stack_pos(program, offset, "t3");
+    program.push_inst(Inst::Sw(reg.to_string(), pos));
+}
+
 pub trait GenerateAsm {
     fn generate(&self, asm: &mut Program, cx: &mut Context);
 }
@@ -101,14 +138,10 @@ impl GenerateAsm for ir::Program {
             cx.set_symbol(global, AsmValue::GlobalVar(value_name.clone()));
             program.push_inst(Inst::Directive(format!("  .globl {}", value_name)));
             program.push_inst(Inst::Lable(format!("{}:", value_name)));
-            if is_zero_init {
-                program.push_inst(Inst::Directive("  .zero 4".to_string()));
-            } else {
-                let val = match alloc_value.kind() {
-                    ValueKind::Integer(integer) => integer,
-                    _ => unreachable!(),
-                };
-                program.push_inst(Inst::Directive(format!("  .word {}", val.value())));
+            let mut zeros = 0;
+            emit_global_init(self, &alloc_value, program, &mut zeros);
+            if zeros > 0 {
+                program.push_inst(Inst::Directive(format!("  .zero {}", zeros)));
             }
             program.newline();
         }
@@ -125,7 +158,7 @@ impl GenerateAsm for ir::Program {
             let func_data = self.func(func);
             let func_name = cx.function_table.get(&func).unwrap();
             // skip buildin declare function
-            if let None = func_data.layout().entry_bb() {
+            if func_data.layout().entry_bb().is_none() {
                 continue;
             }
             program.push_inst(Inst::Directive("  .text".to_string()));
@@ -137,16 +170,76 @@ impl GenerateAsm for ir::Program {
     }
 }
 
+/// emit `.word` for each element, runs of zero are merged into one `.zero`
+fn emit_global_init(ir: &ir::Program, init: &ValueData, program: &mut Program, zeros: &mut usize) {
+    match init.kind() {
+        ValueKind::Integer(int) if int.value() == 0 => *zeros += 4,
+        ValueKind::Integer(int) => {
+            if *zeros > 0 {
+                program.push_inst(Inst::Directive(format!("  .zero {}", zeros)));
+                *zeros = 0;
+            }
+            program.push_inst(Inst::Directive(format!("  .word {}", int.value())));
+        }
+        ValueKind::ZeroInit(_) => *zeros += init.ty().size(),
+        ValueKind::Aggregate(aggregate) => {
+            for &elem in aggregate.elems() {
+                emit_global_init(ir, &ir.borrow_value(elem), program, zeros);
+            }
+        }
+        _ => unreachable!("invalid global initializer: {:?}", init),
+    }
+}
+
 impl GenerateAsm for FunctionData {
     fn generate(&self, program: &mut Program, cx: &mut Context) {
-        let (stack_size, has_func_call) = calculate_stack_size(self);
+        cx.register_manager.allocate(self);
+        let (stack_size, has_func_call, args_size) =
+            calculate_stack_size(self, &cx.register_manager);
         cx.stack_size = stack_size;
+        // the bottom of stack is for the args passed on stack
+        cx.stack_used_size = args_size;
         if has_func_call {
             cx.ra_pos = Some(stack_size - 4);
         }
+        // callee-saved registers are saved right under ra
+        let saved_top = cx.ra_pos.unwrap_or(stack_size);
+        cx.saved_regs = cx
+            .register_manager
+            .saved_registers()
+            .iter()
+            .enumerate()
+            .map(|(i, reg)| (reg.to_string(), saved_top - (i + 1) * 4))
+            .collect();
+        let spill_pos = cx.get_useful_space(cx.register_manager.spill_count() * 4);
+        let locations: Vec<_> = cx.register_manager.locations().clone().into_iter().collect();
+        for (value, location) in locations {
+            let symbol = match location {
+                Location::Register(reg) => AsmValue::Register(reg.to_string()),
+                Location::Spill(i) => AsmValue::Stack(spill_pos + i * 4),
+            };
+            cx.set_symbol(value, symbol);
+        }
 
         let mut is_first_block = true;
-        prologue(program, &cx);
+        prologue(program, cx);
+
+        // params come in a0 ~ a7, the rest on the stack of the caller
+        let moves = self
+            .params()
+            .iter()
+            .enumerate()
+            .filter(|(_, &param)| !self.dfg().value(param).used_by().is_empty())
+            .map(|(i, param)| {
+                let src = if i <= 7 {
+                    AsmValue::Register(format!("a{}", i))
+                } else {
+                    AsmValue::Stack(cx.stack_size + (i - 8) * 4)
+                };
+                (cx.get_symbol(param).unwrap().clone(), src)
+            })
+            .collect();
+        parallel_move(program, moves);
         for (&bb, node) in self.layout().bbs() {
             if !is_first_block {
                 let bb_name = bb_name!(self, bb);
@@ -165,40 +258,53 @@ impl GenerateAsm for FunctionData {
 #[inline]
 fn stack_size(ty: &Type) -> usize {
     match ty.kind() {
-        TypeKind::Int32 | TypeKind::Unit => ty.size(),
-        TypeKind::Array(_, _) => todo!(),
-        TypeKind::Pointer(val) => val.size(),
+        TypeKind::Int32 | TypeKind::Unit | TypeKind::Array(_, _) | TypeKind::Pointer(_) => {
+            ty.size()
+        }
         TypeKind::Function(_, ty) => ty.size(),
     }
 }
 
-fn calculate_stack_size(function_data: &FunctionData) -> (usize, bool) {
+/// alloc returns a pointer, the space is for the pointee
+#[inline]
+fn alloc_size(ty: &Type) -> usize {
+    match ty.kind() {
+        TypeKind::Pointer(base) => stack_size(base),
+        _ => unreachable!("alloc should be a pointer: {}", ty),
+    }
+}
+
+/// returns (stack size, has function call, size of args passed on stack)
+fn calculate_stack_size(
+    function_data: &FunctionData,
+    registers: &RegisterManager,
+) -> (usize, bool, usize) {
     let mut has_func_call = false;
     let mut max_func_args_len = 0;
-    let mut size = 0;
-    for (&_bb, node) in function_data.layout().bbs() {
+    // values which got no register, and callee-saved registers to save
+    let mut size = (registers.spill_count() + registers.saved_registers().len()) * 4;
+    for node in function_data.layout().bbs().nodes() {
         for &inst in node.insts().keys() {
             let value_data = function_data.dfg().value(inst);
             match value_data.kind() {
                 ValueKind::Call(call) => {
                     has_func_call = true;
                     max_func_args_len = max(max_func_args_len, call.args().len());
-                    size += stack_size(value_data.ty());
                 }
-                _ => size += stack_size(value_data.ty()),
+                ValueKind::Alloc(_) => size += alloc_size(value_data.ty()),
+                _ => {}
             }
         }
     }
-    if max_func_args_len > 8 {
-        size += (max_func_args_len - 8) * 4;
-    }
+    let args_size = max_func_args_len.saturating_sub(8) * 4;
+    size += args_size;
     if has_func_call {
         size += 4;
     }
-    if size % 16 == 0 {
-        (size, has_func_call)
+    if size.is_multiple_of(16) {
+        (size, has_func_call, args_size)
     } else {
-        (size + 16 - (size % 16), has_func_call)
+        (size + 16 - (size % 16), has_func_call, args_size)
     }
 }
 fn prologue(program: &mut Program, cx: &Context) {
@@ -222,7 +328,10 @@ fn prologue(program: &mut Program, cx: &Context) {
         ));
     }
     if let Some(ra_pos) = cx.ra_pos {
-        program.push_inst(Inst::Sw("ra".to_string(), format!("{}(sp)", ra_pos)));
+        store_to_stack(program, "ra", ra_pos);
+    }
+    for (reg, pos) in &cx.saved_regs {
+        store_to_stack(program, reg, *pos);
     }
     program.newline();
 }
@@ -234,7 +343,10 @@ fn epilogue(program: &mut Program, cx: &Context) {
     program.push_inst(Inst::Comment("# epilogue".to_string()));
 
     if let Some(ra_pos) = cx.ra_pos {
-        program.push_inst(Inst::Lw("ra".to_string(), format!("{}(sp)", ra_pos)));
+        AsmValue::Stack(ra_pos).load_to(program, "ra");
+    }
+    for (reg, pos) in &cx.saved_regs {
+        AsmValue::Stack(*pos).load_to(program, reg);
     }
     // addi -> 2^12 [-2048, 2047]
     if cx.stack_size < 2047 {
@@ -262,159 +374,102 @@ fn emit(func_data: &FunctionData, value: Value, program: &mut Program, cx: &mut
             let imm = int.value();
             cx.set_symbol(value, AsmValue::Const(imm));
         }
+        // any value will do
+        ValueKind::Undef(_) => cx.set_symbol(value, AsmValue::Const(0)),
         ValueKind::Binary(binary) => {
             program.push_inst(Inst::Comment("# binary".to_string()));
-            if let None = cx.get_symbol(&binary.lhs()) {
-                emit(func_data, binary.lhs(), program, cx);
-            }
-            let lhs_value = cx.get_symbol(&binary.lhs()).unwrap().clone();
-            lhs_value.load_to(program, "t0");
-            if let None = cx.get_symbol(&binary.rhs()) {
-                emit(func_data, binary.rhs(), program, cx);
-            }
-            let rhs_value = cx.get_symbol(&binary.rhs()).unwrap().clone();
-            rhs_value.load_to(program, "t1");
+            let lhs = operand(func_data, binary.lhs(), program, cx, "t0");
+            let rhs = operand(func_data, binary.rhs(), program, cx, "t1");
+            let dst = dest_reg(cx, value, "t0");
 
             match binary.op() {
                 BinaryOp::Eq => {
-                    program.push_inst(Inst::Sub(
-                        "t0".to_string(),
-                        "t0".to_string(),
-                        "t1".to_string(),
-                    ));
-                    program.push_inst(Inst::Seqz("t0".to_string(), "t0".to_string()));
+                    program.push_inst(Inst::Sub(dst.clone(), lhs, rhs));
+                    program.push_inst(Inst::Seqz(dst.clone(), dst.clone()));
                 }
                 BinaryOp::NotEq => {
-                    program.push_inst(Inst::Sub(
-                        "t0".to_string(),
-                        "t0".to_string(),
-                        "t1".to_string(),
-                    ));
-                    program.push_inst(Inst::Snez("t0".to_string(), "t0".to_string()));
+                    program.push_inst(Inst::Sub(dst.clone(), lhs, rhs));
+                    program.push_inst(Inst::Snez(dst.clone(), dst.clone()));
                 }
-                BinaryOp::Sub => program.push_inst(Inst::Sub(
-                    "t0".to_string(),
-                    "t0".to_string(),
-                    "t1".to_string(),
-                )),
-                BinaryOp::Mul => program.push_inst(Inst::Mul(
-                    "t0".to_string(),
-                    "t0".to_string(),
-                    "t1".to_string(),
-                )),
-                BinaryOp::Add => program.push_inst(Inst::Add(
-                    "t0".to_string(),
-                    "t0".to_string(),
-                    "t1".to_string(),
-                )),
-                BinaryOp::Div => program.push_inst(Inst::Div(
-                    "t0".to_string(),
-                    "t0".to_string(),
-                    "t1".to_string(),
-                )),
+                BinaryOp::Sub => program.push_inst(Inst::Sub(dst.clone(), lhs, rhs)),
+                BinaryOp::Mul => program.push_inst(Inst::Mul(dst.clone(), lhs, rhs)),
+                BinaryOp::Add => program.push_inst(Inst::Add(dst.clone(), lhs, rhs)),
+                BinaryOp::Div => program.push_inst(Inst::Div(dst.clone(), lhs, rhs)),
+                BinaryOp::Mod => program.push_inst(Inst::Rem(dst.clone(), lhs, rhs)),
                 BinaryOp::Gt => {
-                    program.push_inst(Inst::Sgt(
-                        "t0".to_string(),
-                        "t0".to_string(),
-                        "t1".to_string(),
-                    ));
-                    program.push_inst(Inst::Snez("t0".to_string(), "t0".to_string()));
+                    program.push_inst(Inst::Sgt(dst.clone(), lhs, rhs));
+                    program.push_inst(Inst::Snez(dst.clone(), dst.clone()));
                 }
                 BinaryOp::Lt => {
-                    program.push_inst(Inst::Slt(
-                        "t0".to_string(),
-                        "t0".to_string(),
-                        "t1".to_string(),
-                    ));
-                    program.push_inst(Inst::Snez("t0".to_string(), "t0".to_string()));
+                    program.push_inst(Inst::Slt(dst.clone(), lhs, rhs));
+                    program.push_inst(Inst::Snez(dst.clone(), dst.clone()));
                 }
                 BinaryOp::Ge => {
-                    program.push_inst(Inst::Slt(
-                        "t0".to_string(),
-                        "t0".to_string(),
-                        "t1".to_string(),
-                    ));
-                    program.push_inst(Inst::Snez("t0".to_string(), "t0".to_string()));
+                    // a >= b is !(a < b)
+                    program.push_inst(Inst::Slt(dst.clone(), lhs, rhs));
+                    program.push_inst(Inst::Seqz(dst.clone(), dst.clone()));
                 }
                 BinaryOp::Le => {
-                    program.push_inst(Inst::Sgt(
-                        "t0".to_string(),
-                        "t0".to_string(),
-                        "t1".to_string(),
-                    ));
-                    program.push_inst(Inst::Snez("t0".to_string(), "t0".to_string()));
+                    // a <= b is !(a > b)
+                    program.push_inst(Inst::Sgt(dst.clone(), lhs, rhs));
+                    program.push_inst(Inst::Seqz(dst.clone(), dst.clone()));
                 }
                 _ => unimplemented!("op: {}", binary.op()),
             }
-
-            let pos = cx.get_useful_space(stack_size(value_data.ty()));
-            program.push_inst(Inst::Sw("t0".to_string(), pos.clone()));
-            cx.set_symbol(value, AsmValue::Value(pos));
+            save_result(program, cx, value, &dst);
         }
         ValueKind::Alloc(_) => {
             program.push_inst(Inst::Comment("# alloc".to_string()));
-            let pos = cx.get_useful_space(stack_size(value_data.ty()));
-            cx.set_symbol(value, AsmValue::Value(pos));
+            let pos = cx.get_useful_space(alloc_size(value_data.ty()));
+            cx.set_symbol(value, AsmValue::Stack(pos));
         }
         ValueKind::Load(load) => {
             program.push_inst(Inst::Comment("# load".to_string()));
-            cx.get_symbol(&load.src()).unwrap().load_to(program, "t0");
-            let pos = cx.get_useful_space(stack_size(value_data.ty()));
-
-            program.push_inst(Inst::Sw("t0".to_string(), pos.clone()));
-            cx.set_symbol(value, AsmValue::Value(pos));
+            let dst = dest_reg(cx, value, "t0");
+            load_from_ptr(func_data, load.src(), program, cx, &dst);
+            save_result(program, cx, value, &dst);
         }
         ValueKind::Store(store) => {
             program.push_inst(Inst::Comment("# store".to_string()));
-
-            if let None = cx.get_symbol(&store.value()) {
-                emit(func_data, store.value(), program, cx);
-            }
-            let source_pos = cx
-                .get_symbol(&store.value())
-                .unwrap()
-                .clone()
-                .load_to(program, "t0");
-
-            if let None = cx.get_symbol(&store.dest()) {
-                emit(func_data, store.dest(), program, cx);
-            }
-            if let AsmValue::Value(dest) = cx.get_symbol(&store.dest()).unwrap().clone() {
-                program.push_inst(Inst::Sw(source_pos.to_string(), dest.to_string()));
-            }
+            let src = operand(func_data, store.value(), program, cx, "t0");
+            store_to_ptr(func_data, &src, store.dest(), program, cx, "t1");
+        }
+        ValueKind::GetElemPtr(gep) => {
+            program.push_inst(Inst::Comment("# getelemptr".to_string()));
+            emit_ptr_calc(func_data, value, gep.src(), gep.index(), program, cx);
+        }
+        ValueKind::GetPtr(gp) => {
+            program.push_inst(Inst::Comment("# getptr".to_string()));
+            emit_ptr_calc(func_data, value, gp.src(), gp.index(), program, cx);
         }
         ValueKind::Call(call) => {
             program.push_inst(Inst::Comment("# call".to_string()));
 
+            let mut moves = vec![];
             for (i, arg) in call.args().iter().enumerate() {
-                if let None = cx.get_symbol(arg) {
+                if cx.get_symbol(arg).is_none() {
                     emit(func_data, *arg, program, cx);
                 }
+                let arg_value = cx.get_symbol(arg).unwrap().clone();
                 if i <= 7 {
-                    let pos = format!("a{}", i);
-                    cx.get_symbol(arg).unwrap().load_to(program, pos.as_str());
+                    moves.push((AsmValue::Register(format!("a{}", i)), arg_value));
                 } else {
-                    let arg_data = func_data.dfg().value(*arg);
-                    let pos = cx.get_useful_space(stack_size(arg_data.ty()));
-                    cx.get_symbol(arg).unwrap().load_to(program, pos.as_str());
+                    // sp + 0 => arg 8, sp + 4 => arg 9 ..., before a0 ~ a7 change
+                    move_value(program, &AsmValue::Stack((i - 8) * 4), &arg_value);
                 }
             }
+            parallel_move(program, moves);
 
             let callee = cx.function_table.get(&call.callee()).unwrap();
-            program.push_inst(Inst::Call(format!("call {}", callee)));
-            // save return value
-            let return_val_pos = cx.get_useful_space(stack_size(&value_data.ty()));
+            program.push_inst(Inst::Call(callee.clone()));
             if !value_data.ty().is_unit() {
-                program.push_inst(Inst::Sw("a0".to_string(), return_val_pos.to_string()));
+                save_result(program, cx, value, "a0");
             }
-
-            cx.symbol_table
-                .insert(value, AsmValue::Value(return_val_pos));
         }
         ValueKind::Return(ret) => {
             program.push_inst(Inst::Comment("# return".to_string()));
             if let Some(ret_val) = ret.value() {
-                if let None = cx.get_symbol(&ret_val) {
+                if cx.get_symbol(&ret_val).is_none() {
                     emit(func_data, ret_val, program, cx);
                 }
                 cx.get_symbol(&ret_val).unwrap().load_to(program, "a0");
@@ -425,37 +480,226 @@ fn emit(func_data: &FunctionData, value: Value, program: &mut Program, cx: &mut
         }
         ValueKind::Branch(branch) => {
             program.push_inst(Inst::Comment("# branch".to_string()));
-            if let None = cx.get_symbol(&branch.cond()) {
-                emit(func_data, branch.cond(), program, cx);
-            }
-            let cond = cx.get_symbol(&branch.cond()).unwrap().clone();
-            cond.load_to(program, "t0");
+            let cond = operand(func_data, branch.cond(), program, cx, "t0");
 
             let true_bb_name = bb_name!(func_data, branch.true_bb());
             let false_bb_name = bb_name!(func_data, branch.false_bb());
-            program.push_inst(Inst::Bnez("t0".to_string(), true_bb_name));
-            program.push_inst(Inst::J(false_bb_name));
+            if branch.true_args().is_empty() {
+                program.push_inst(Inst::Bnez(cond, true_bb_name));
+                pass_args(func_data, branch.false_bb(), branch.false_args(), program, cx);
+                program.push_inst(Inst::J(false_bb_name));
+            } else {
+                // the true edge passes its arguments under a label of its own
+                cx.edge_count += 1;
+                let edge_name = format!("{}.{}", true_bb_name, cx.edge_count);
+                program.push_inst(Inst::Bnez(cond, edge_name.clone()));
+                pass_args(func_data, branch.false_bb(), branch.false_args(), program, cx);
+                program.push_inst(Inst::J(false_bb_name));
+                program.push_inst(Inst::Lable(format!("{}:", edge_name)));
+                pass_args(func_data, branch.true_bb(), branch.true_args(), program, cx);
+                program.push_inst(Inst::J(true_bb_name));
+            }
         }
         ValueKind::Jump(jump) => {
             let target_bb_name = bb_name!(func_data, jump.target());
+            pass_args(func_data, jump.target(), jump.args(), program, cx);
             program.push_inst(Inst::J(target_bb_name));
         }
-        ValueKind::FuncArgRef(arg) => {
-            program.push_inst(Inst::Comment(format!(
-                "# func arg ref index: {}",
-                arg.index()
-            )));
-            // args on reg a0 ~ a7
-            // if len(args) > 8 => on stack
-            // sp + 0 => 9
-            // sp + n => 10 ...
-            if arg.index() <= 7 {
-                let pos = format!("a{}", arg.index());
-                cx.set_symbol(value, AsmValue::Register(pos));
-            } else {
-                // TODO
+        _ => unimplemented!("{:?}", value_data),
+    }
+}
+
+/// moves `args` into the params of `target` at once, an argument may be a
+/// param of `target` itself, as when two variables swap
+fn pass_args(
+    func_data: &FunctionData,
+    target: BasicBlock,
+    args: &[Value],
+    program: &mut Program,
+    cx: &mut Context,
+) {
+    let params = func_data.dfg().bb(target).params();
+    let mut moves = vec![];
+    for (param, arg) in params.iter().zip(args) {
+        if cx.get_symbol(arg).is_none() {
+            emit(func_data, *arg, program, cx);
+        }
+        let dst = cx.get_symbol(param).unwrap().clone();
+        moves.push((dst, cx.get_symbol(arg).unwrap().clone()));
+    }
+    parallel_move(program, moves);
+}
+
+/// dst = src, `dst` is a register or a stack slot
+fn move_value(program: &mut Program, dst: &AsmValue, src: &AsmValue) {
+    match (dst, src) {
+        (AsmValue::Register(reg), _) => {
+            src.load_to(program, reg);
+        }
+        (AsmValue::Stack(pos), AsmValue::Register(reg)) => store_to_stack(program, reg, *pos),
+        (AsmValue::Stack(pos), _) => {
+            let reg = src.load_to(program, "t1");
+            store_to_stack(program, &reg, *pos);
+        }
+        _ => unreachable!("can't move into {:?}", dst),
+    }
+}
+
+/// does the `(dst, src)` moves as if all at once, a move waits until its
+/// destination is no longer the source of another one, a cycle is broken by
+/// keeping one destination in t0
+fn parallel_move(program: &mut Program, moves: Vec<(AsmValue, AsmValue)>) {
+    // constants and globals are in the way of nothing, they go last
+    let (mut pending, rest): (Vec<_>, Vec<_>) = moves
+        .into_iter()
+        .filter(|(dst, src)| dst != src)
+        .partition(|(_, src)| matches!(src, AsmValue::Register(_) | AsmValue::Stack(_)));
+    while !pending.is_empty() {
+        let free = pending
+            .iter()
+            .position(|(dst, _)| pending.iter().all(|(_, src)| src != dst));
+        match free {
+            Some(i) => {
+                let (dst, src) = pending.remove(i);
+                move_value(program, &dst, &src);
+            }
+            None => {
+                let tmp = AsmValue::Register("t0".to_string());
+                let dst = pending[0].0.clone();
+                move_value(program, &tmp, &dst);
+                for (_, src) in &mut pending {
+                    if *src == dst {
+                        *src = tmp.clone();
+                    }
+                }
             }
         }
-        _ => unimplemented!("{:?}", value_data),
     }
+    for (dst, src) in rest {
+        move_value(program, &dst, &src);
+    }
+}
+
+/// a register holding `value`, it's loaded into `tmp` unless it lives in one
+fn operand(
+    func_data: &FunctionData,
+    value: Value,
+    program: &mut Program,
+    cx: &mut Context,
+    tmp: &str,
+) -> String {
+    if cx.get_symbol(&value).is_none() {
+        emit(func_data, value, program, cx);
+    }
+    match cx.get_symbol(&value).unwrap().clone() {
+        AsmValue::Register(reg) => reg,
+        symbol => symbol.load_to(program, tmp),
+    }
+}
+
+/// the register `value` is computed into, `tmp` when it's spilled
+fn dest_reg(cx: &Context, value: Value, tmp: &str) -> String {
+    match cx.get_symbol(&value) {
+        Some(AsmValue::Register(reg)) => reg.clone(),
+        _ => tmp.to_string(),
+    }
+}
+
+/// puts `reg` where `value` lives
+fn save_result(program: &mut Program, cx: &Context, value: Value, reg: &str) {
+    let dst = cx.get_symbol(&value).unwrap();
+    move_value(program, dst, &AsmValue::Register(reg.to_string()));
+}
+
+/// a register holding the address `ptr` points to, that is `reg` unless
+/// `ptr` is a value which lives in a register
+fn load_addr(
+    func_data: &FunctionData,
+    ptr: Value,
+    program: &mut Program,
+    cx: &mut Context,
+    reg: &str,
+) -> String {
+    if ptr.is_global() {
+        if let Some(AsmValue::GlobalVar(label)) = cx.get_symbol(&ptr) {
+            program.push_inst(Inst::La(reg.to_string(), label.to_string()));
+        }
+        return reg.to_string();
+    }
+    if !matches!(func_data.dfg().value(ptr).kind(), ValueKind::Alloc(_)) {
+        return operand(func_data, ptr, program, cx, reg);
+    }
+    if cx.get_symbol(&ptr).is_none() {
+        emit(func_data, ptr, program, cx);
+    }
+    match cx.get_symbol(&ptr) {
+        Some(&AsmValue::Stack(offset)) => stack_addr(program, offset, reg),
+        _ => unreachable!("alloc should be on stack"),
+    }
+    reg.to_string()
+}
+
+/// reg = *ptr
+fn load_from_ptr(func_data: &FunctionData, ptr: Value, program: &mut Program, cx: &mut Context, reg: &str) {
+    if ptr.is_global() || matches!(func_data.dfg().value(ptr).kind(), ValueKind::Alloc(_)) {
+        if cx.get_symbol(&ptr).is_none() {
+            emit(func_data, ptr, program, cx);
+        }
+        cx.get_symbol(&ptr).unwrap().clone().load_to(program, reg);
+    } else {
+        let addr = load_addr(func_data, ptr, program, cx, reg);
+        program.push_inst(Inst::Lw(reg.to_string(), format!("0({})", addr)));
+    }
+}
+
+/// *ptr = reg, `tmp` is used to hold the address
+fn store_to_ptr(
+    func_data: &FunctionData,
+    reg: &str,
+    ptr: Value,
+    program: &mut Program,
+    cx: &mut Context,
+    tmp: &str,
+) {
+    if !ptr.is_global() && matches!(func_data.dfg().value(ptr).kind(), ValueKind::Alloc(_)) {
+        if cx.get_symbol(&ptr).is_none() {
+            emit(func_data, ptr, program, cx);
+        }
+        if let Some(AsmValue::Stack(offset)) = cx.get_symbol(&ptr) {
+            let pos = stack_pos(program, *offset, tmp);
+            program.push_inst(Inst::Sw(reg.to_string(), pos));
+        }
+    } else {
+        let addr = load_addr(func_data, ptr, program, cx, tmp);
+        program.push_inst(Inst::Sw(reg.to_string(), format!("0({})", addr)));
+    }
+}
+
+/// value = src + index * size of the pointee of value,
+/// both getelemptr and getptr return a pointer to the element
+fn emit_ptr_calc(
+    func_data: &FunctionData,
+    value: Value,
+    src: Value,
+    index: Value,
+    program: &mut Program,
+    cx: &mut Context,
+) {
+    let value_data = func_data.dfg().value(value);
+    let base = load_addr(func_data, src, program, cx, "t0");
+    let index = operand(func_data, index, program, cx, "t1");
+
+    let elem_size = match value_data.ty().kind() {
+        TypeKind::Pointer(base) => base.size(),
+        _ => unreachable!("getelemptr/getptr should return a pointer"),
+    };
+    program.push_inst(Inst::Li("t2".to_string(), elem_size as i32));
+    program.push_inst(Inst::Mul(
+        "t1".to_string(),
+        index,
+        "t2".to_string(),
+    ));
+    let dst = dest_reg(cx, value, "t0");
+    program.push_inst(Inst::Add(dst.clone(), base, "t1".to_string()));
+    save_result(program, cx, value, &dst);
 }
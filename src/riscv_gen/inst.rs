@@ -37,13 +37,13 @@ pub enum Inst {
 }
 
 impl Inst {
-    pub fn to_isa(self) -> String {
+    pub fn to_isa(&self) -> String {
         match self {
             Inst::Beqz(a, b) => format!("  beqz {}, {}", a, b),
             Inst::Bnez(a, b) => format!("  bnez {}, {}", a, b),
             Inst::J(pos) => format!("  j {}", pos),
             Inst::Call(func) => format!("  call {}", func),
-            Inst::Ret => format!("  ret"),
+            Inst::Ret => "  ret".to_string(),
             Inst::Lw(a, b) => format!("  lw {}, {}", a,b ),
             Inst::Sw(a, b) =>format!("  sw {}, {}", a,b ),
             Inst::Add(a, b, c) => format!("  add {}, {}, {}", a, b, c),
@@ -66,9 +66,9 @@ impl Inst {
             Inst::La(a, b) => format!("  la {}, {}", a, b),
             Inst::Mv(a, b) => format!("  mv {}, {}", a, b),
             Inst::NewLine => "".to_string(),
-            Inst::Directive(s) => s,
-            Inst::Comment(s) => s,
-            Inst::Lable(f) => f,
+            Inst::Directive(s) => s.clone(),
+            Inst::Comment(s) => s.clone(),
+            Inst::Lable(f) => f.clone(),
         }
     }
 }
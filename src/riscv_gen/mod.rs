@@ -1,4 +1,5 @@
 use crate::riscv_gen::context::Context;
+use koopa::ir::Type;
 use gen::*;
 
 mod context;
@@ -12,12 +13,14 @@ pub enum Error {}
 
 pub type Result<T> = std::result::Result<T, Error>;
 
-pub fn generate_riscv<'a>(program: koopa::ir::Program, args: Vec<String>) -> Result<Program> {
+pub fn generate_riscv(program: koopa::ir::Program, peephole: bool) -> Result<Program> {
+    // riscv32
+    Type::set_ptr_size(4);
     let mut riscv = gen::Program::new();
     let mut cx = Context::new();
     program.generate(&mut riscv, &mut cx);
 
-    if args.contains(&"-p".to_string()) {
+    if peephole {
         riscv = optimizer::peephole(riscv);
     }
     Ok(riscv)
@@ -18,15 +18,21 @@ pub fn peephole(mut program: Program) -> Program {
             | Inst::Lable(_)
             | Inst::Directive(_) => memo.clear(),
             Inst::Sw(rs, rd) => {
-                memo.insert(rs, rd);
-            }
-            Inst::Lw(rs, rd) => {
-                if let Some(last_rd) = memo.get(&rs) {
-                    if *last_rd == rd {
-                        program.insts[i] = Inst::NewLine;
-                    }
+                if rd.ends_with("(sp)") {
+                    // other registers no longer hold the value of rd
+                    memo.retain(|_, addr| *addr != rd);
+                    memo.insert(rs, rd);
+                } else {
+                    // store through a pointer may overwrite any slot
+                    memo.clear();
                 }
             }
+            Inst::Lw(rs, rd) => match memo.get(&rs) {
+                Some(last_rd) if *last_rd == rd => program.insts[i] = Inst::NewLine,
+                _ => {
+                    memo.remove(&rs);
+                }
+            },
             Inst::Add(rs, _, _)
             | Inst::Addi(rs, _, _)
             | Inst::Sub(rs, _, _)
@@ -69,7 +75,7 @@ mod test {
 
     use super::peephole;
 
-    fn print_inst(result: &Vec<Inst>, wanted: &Vec<Inst>) {
+    fn print_inst(result: &[Inst], wanted: &[Inst]) {
         println!("=============");
         for i in 0..max(result.len(), wanted.len()) {
             println!("inst: {:?}", i);
@@ -95,7 +101,7 @@ mod test {
             .for_each(|(result, wanted)| {
                 assert_eq!(*result, wanted);
             });
-        println!("");
+        println!();
     }
 
     #[test]
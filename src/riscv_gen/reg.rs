@@ -1,12 +1,126 @@
+use std::collections::HashMap;
+
+use koopa::ir::{FunctionData, ValueKind, Value};
+
+use crate::analysis::cfg::Cfg;
+use crate::analysis::live_variable::{LiveVariables, ValueLife};
+
+/// where the allocator puts a value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Location {
+    Register(&'static str),
+    // index of a 4 bytes slot in the spill area
+    Spill(usize),
+}
+
+// t0 ~ t3 are left to the code generator as scratch
+const CALLER_SAVED: [&str; 11] = ["t4", "t5", "t6", "a0", "a1", "a2", "a3", "a4", "a5", "a6", "a7"];
+const CALLEE_SAVED: [&str; 11] = [
+    "s1", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11",
+];
+
 pub(crate) struct RegisterManager {
+    // registers held by a live value
     registers: Registers,
+    locations: HashMap<Value, Location>,
+    spills: usize,
+    // callee-saved registers handed out, the prologue saves them
+    saved: Vec<&'static str>,
 }
 
 impl RegisterManager {
     pub fn new() -> Self {
         RegisterManager {
             registers: Registers::default(),
+            locations: HashMap::new(),
+            spills: 0,
+            saved: vec![],
+        }
+    }
+
+    /// linear scan of Poletto and Sarkar over the live intervals of `func`,
+    /// a value live across a call only takes a callee-saved register, when
+    /// none is free the interval which ends last is spilled
+    pub fn allocate(&mut self, func: &FunctionData) {
+        *self = RegisterManager::new();
+        let live = LiveVariables::new(func, &Cfg::new(func));
+        let calls: Vec<_> = func
+            .layout()
+            .bbs()
+            .nodes()
+            .flat_map(|node| node.insts().keys())
+            .filter(|&&inst| matches!(func.dfg().value(inst).kind(), ValueKind::Call(_)))
+            .map(|&inst| live.position(inst))
+            .collect();
+
+        // sorted by end
+        let mut active: Vec<(ValueLife, &'static str)> = vec![];
+        for &life in live.lives() {
+            active.retain(|&(other, reg)| {
+                let expired = other.end < life.start;
+                if expired {
+                    *self.registers.get_mut(reg) = false;
+                }
+                !expired
+            });
+
+            let crosses_call = calls.iter().any(|&call| life.start < call && call < life.end);
+            let candidates: Vec<_> = if crosses_call {
+                CALLEE_SAVED.to_vec()
+            } else {
+                CALLER_SAVED.iter().chain(&CALLEE_SAVED).copied().collect()
+            };
+            let reg = match candidates.iter().find(|&&reg| !*self.registers.get_mut(reg)) {
+                Some(&reg) => reg,
+                None => {
+                    let victim = active
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, (_, reg))| candidates.contains(reg))
+                        .max_by_key(|(_, (other, _))| other.end)
+                        .map(|(i, &(other, reg))| (i, other, reg));
+                    match victim {
+                        Some((i, other, reg)) if other.end > life.end => {
+                            active.remove(i);
+                            self.spill(other.val);
+                            *self.registers.get_mut(reg) = false;
+                            reg
+                        }
+                        _ => {
+                            self.spill(life.val);
+                            continue;
+                        }
+                    }
+                }
+            };
+            *self.registers.get_mut(reg) = true;
+            if CALLEE_SAVED.contains(&reg) && !self.saved.contains(&reg) {
+                self.saved.push(reg);
+            }
+            self.locations.insert(life.val, Location::Register(reg));
+            let at = active.partition_point(|(other, _)| other.end <= life.end);
+            active.insert(at, (life, reg));
         }
+        self.saved.sort_by_key(|reg| CALLEE_SAVED.iter().position(|r| r == reg));
+    }
+
+    fn spill(&mut self, value: Value) {
+        self.locations.insert(value, Location::Spill(self.spills));
+        self.spills += 1;
+    }
+
+    /// every value which may sit in a register has a location
+    pub fn locations(&self) -> &HashMap<Value, Location> {
+        &self.locations
+    }
+
+    pub fn spill_count(&self) -> usize {
+        self.spills
+    }
+
+    /// callee-saved registers in use
+    pub fn saved_registers(&self) -> &[&'static str] {
+        &self.saved
     }
 }
 
@@ -57,3 +171,125 @@ pub(crate) struct Registers {
     pub t5: bool,
     pub t6: bool,
 }
+
+impl Registers {
+    pub fn get_mut(&mut self, name: &str) -> &mut bool {
+        match name {
+            "ra" => &mut self.ra,
+            "sp" => &mut self.sp,
+            "gp" => &mut self.gp,
+            "tp" => &mut self.tp,
+            "t0" => &mut self.t0,
+            "t1" => &mut self.t1,
+            "t2" => &mut self.t2,
+            "s0" | "fp" => &mut self.fp,
+            "s1" => &mut self.s1,
+            "a0" => &mut self.a0,
+            "a1" => &mut self.a1,
+            "a2" => &mut self.a2,
+            "a3" => &mut self.a3,
+            "a4" => &mut self.a4,
+            "a5" => &mut self.a5,
+            "a6" => &mut self.a6,
+            "a7" => &mut self.a7,
+            "s2" => &mut self.s2,
+            "s3" => &mut self.s3,
+            "s4" => &mut self.s4,
+            "s5" => &mut self.s5,
+            "s6" => &mut self.s6,
+            "s7" => &mut self.s7,
+            "s8" => &mut self.s8,
+            "s9" => &mut self.s9,
+            "s10" => &mut self.s10,
+            "s11" => &mut self.s11,
+            "t3" => &mut self.t3,
+            "t4" => &mut self.t4,
+            "t5" => &mut self.t5,
+            "t6" => &mut self.t6,
+            _ => unreachable!("unknown register: {}", name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use koopa::ir::FunctionData;
+
+    use super::{Location, RegisterManager, CALLEE_SAVED};
+    use crate::analysis::cfg::Cfg;
+    use crate::analysis::live_variable::LiveVariables;
+    use crate::analysis::test_util::{only_func, parse, value};
+
+    /// values whose intervals overlap never share a register
+    fn check(func: &FunctionData, rm: &RegisterManager) {
+        let live = LiveVariables::new(func, &Cfg::new(func));
+        let lives = live.lives();
+        for (i, a) in lives.iter().enumerate() {
+            for b in &lives[i + 1..] {
+                let overlap = a.start <= b.end && b.start <= a.end;
+                let (la, lb) = (rm.locations()[&a.val], rm.locations()[&b.val]);
+                if overlap && matches!(la, Location::Register(_)) {
+                    assert_ne!(la, lb);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn across_call() {
+        let program = parse(
+            r"
+decl @g(): i32
+
+fun @f(@n: i32): i32 {
+%entry:
+  %x = add @n, 1
+  %y = call @g()
+  %z = add %x, %y
+  %w = add %z, @n
+  ret %w
+}",
+        );
+        let func = only_func(&program);
+        let mut rm = RegisterManager::new();
+        rm.allocate(func);
+        check(func, &rm);
+
+        let reg = |name| match rm.locations()[&value(func, name)] {
+            Location::Register(reg) => reg,
+            Location::Spill(_) => panic!("{} is spilled", name),
+        };
+        // live across the call
+        assert!(CALLEE_SAVED.contains(&reg("@n")));
+        assert!(CALLEE_SAVED.contains(&reg("%x")));
+        // not
+        assert!(!CALLEE_SAVED.contains(&reg("%y")));
+        assert!(!CALLEE_SAVED.contains(&reg("%z")));
+        assert_eq!(rm.saved_registers(), ["s1", "s2"]);
+        assert_eq!(rm.spill_count(), 0);
+    }
+
+    #[test]
+    fn spill_under_pressure() {
+        // 30 values live at once, with 22 registers to take
+        let defs: String = (0..30).map(|i| format!("  %v{} = add @n, {}\n", i, i)).collect();
+        let sum: String = (1..30)
+            .map(|i| format!("  %s{} = add %s{}, %v{}\n", i, i - 1, i))
+            .collect();
+        let program = parse(&format!(
+            "fun @f(@n: i32): i32 {{\n%entry:\n{}  %s0 = add %v0, 0\n{}  ret %s29\n}}",
+            defs, sum
+        ));
+        let func = only_func(&program);
+        let mut rm = RegisterManager::new();
+        rm.allocate(func);
+        check(func, &rm);
+        assert!(rm.spill_count() > 0);
+        assert!(rm.spill_count() <= 10);
+        // the sums are short lived and always find a register
+        for i in 0..30 {
+            let location = rm.locations()[&value(func, &format!("%s{}", i))];
+            assert!(matches!(location, Location::Register(_)));
+        }
+    }
+}
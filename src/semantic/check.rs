@@ -0,0 +1,559 @@
+use crate::ast::*;
+use crate::ir_gen::init::{flatten, Initializer};
+
+use super::eval::Evaluate;
+use super::scope::{FuncSig, Scope, Var, VarKind};
+use super::warning::Warning;
+use super::{ErrorKind, Result, Ty};
+
+pub trait Check {
+    type Out;
+
+    fn check<'ast>(&'ast self, scope: &mut Scope<'ast>) -> Result<Self::Out>;
+}
+
+/// operands of arithmetic, conditions, array indices...
+fn expect_int(ty: Ty, span: Span) -> Result<()> {
+    match ty {
+        Ty::Int => Ok(()),
+        Ty::Void => Err(ErrorKind::VoidValue.at(span)),
+        found => Err(ErrorKind::Mismatch {
+            expected: "int".to_string(),
+            found,
+        }
+        .at(span)),
+    }
+}
+
+fn check_int<'ast, T: Check<Out = Ty>>(
+    exp: &'ast T,
+    span: Span,
+    scope: &mut Scope<'ast>,
+) -> Result<()> {
+    let ty = exp.check(scope)?;
+    expect_int(ty, span)
+}
+
+fn const_dims<'ast>(dims: &'ast [ConstExp], scope: &mut Scope<'ast>) -> Result<Vec<usize>> {
+    let mut lens = vec![];
+    for dim in dims {
+        check_int(dim, dim.span(), scope)?;
+        match dim.eval(scope)? {
+            len if len > 0 => lens.push(len as usize),
+            len => return Err(ErrorKind::InvalidArraySize(len).at(dim.span())),
+        }
+    }
+    Ok(lens)
+}
+
+fn var_kind(scope: &Scope) -> VarKind {
+    if scope.in_global_scope() {
+        VarKind::Global
+    } else {
+        VarKind::Local
+    }
+}
+
+fn array_or_int(dims: Vec<usize>) -> Ty {
+    if dims.is_empty() {
+        Ty::Int
+    } else {
+        Ty::Array(dims)
+    }
+}
+
+/// checks every element of the initializer, and evaluates them if `need_const`
+fn check_init<'ast, T: Initializer>(
+    init: &'ast T,
+    dims: &[usize],
+    span: Span,
+    need_const: bool,
+    scope: &mut Scope<'ast>,
+) -> Result<Option<Vec<i32>>> {
+    let init = flatten(init, dims).map_err(|e| ErrorKind::InvalidInit(e.to_string()).at(span))?;
+    for excess in init.excess {
+        let msg = "excess elements in scalar initializer".to_string();
+        scope.warn(Warning::ExcessInitializers, excess, msg);
+    }
+    for exp in init.elems.iter().flatten() {
+        check_int(*exp, exp.span(), scope)?;
+    }
+    if !need_const {
+        return Ok(None);
+    }
+    let mut values = vec![];
+    for exp in init.elems {
+        values.push(match exp {
+            Some(exp) => exp.eval(scope)?,
+            None => 0,
+        });
+    }
+    Ok(Some(values))
+}
+
+impl Check for CompUnit {
+    type Out = ();
+
+    fn check<'ast>(&'ast self, scope: &mut Scope<'ast>) -> Result<Self::Out> {
+        if let Some(ref comp_unit) = *self.comp_unit {
+            comp_unit.check(scope)?;
+        }
+        let result = match &self.global {
+            Global::FuncDef(func) => func.check(scope),
+            Global::FuncDecl(func) => func.check(scope),
+            Global::Decl(decl) => decl.check(scope),
+        };
+        scope.report(result);
+        Ok(())
+    }
+}
+
+impl Check for FuncFParam {
+    type Out = Ty;
+
+    fn check<'ast>(&'ast self, scope: &mut Scope<'ast>) -> Result<Self::Out> {
+        match &self.dims {
+            None => Ok(Ty::Int),
+            Some(dims) => Ok(Ty::Pointer(const_dims(dims, scope)?)),
+        }
+    }
+}
+
+/// return and parameter types of a function
+fn signature<'ast>(
+    func_type: &FuncType,
+    params: &'ast [FuncFParam],
+    scope: &mut Scope<'ast>,
+) -> FuncSig {
+    let ret = match func_type {
+        FuncType::Int => Ty::Int,
+        FuncType::Void => Ty::Void,
+    };
+    let mut tys = vec![];
+    for param in params {
+        let ty = param.check(scope);
+        tys.push(scope.report(ty).unwrap_or(Ty::Int));
+    }
+    FuncSig { ret, params: tys }
+}
+
+impl Check for FuncDecl {
+    type Out = ();
+
+    fn check<'ast>(&'ast self, scope: &mut Scope<'ast>) -> Result<Self::Out> {
+        let sig = signature(&self.func_type, &self.params, scope);
+        scope
+            .add_function(&self.ident, sig, false)
+            .map_err(|e| e.at(self.span))
+    }
+}
+
+impl Check for FuncDef {
+    type Out = ();
+
+    fn check<'ast>(&'ast self, scope: &mut Scope<'ast>) -> Result<Self::Out> {
+        let sig = signature(&self.func_type, &self.params, scope);
+        let (ret, params) = (sig.ret.clone(), sig.params.clone());
+        // added before the body for recursive calls
+        let added = scope.add_function(&self.ident, sig, true);
+        scope.report(added.map_err(|e| e.at(self.span)));
+
+        scope.enter_function(&self.ident, ret);
+        scope.enter_scope();
+        for (param, ty) in self.params.iter().zip(params) {
+            let var = Var {
+                ty,
+                values: None,
+                kind: VarKind::Param,
+                span: param.span,
+                used: false,
+            };
+            let added = scope.add(&param.ident, var);
+            scope.report(added.map_err(|e| e.at(param.span)));
+        }
+        // the body shares the scope of the parameters
+        let falls_through = check_items(&self.block.block_item, scope);
+        if falls_through && matches!(self.func_type, FuncType::Int) {
+            // point at the closing brace
+            let end = Span::new(self.block.span.end - 1, self.block.span.end);
+            let msg = "control reaches end of non-void function".to_string();
+            scope.warn(Warning::ReturnType, end, msg);
+        }
+        scope.exit_scope();
+        scope.exit_function();
+        Ok(())
+    }
+}
+
+/// checks the items of a block in the current scope, returns whether control
+/// can fall through the end of them
+fn check_items<'ast>(items: &'ast [BlockItem], scope: &mut Scope<'ast>) -> bool {
+    let mut falls_through = true;
+    let mut warned = false;
+    for item in items {
+        if !falls_through && !warned {
+            let msg = "code will never be executed".to_string();
+            scope.warn(Warning::UnreachableCode, item.span(), msg);
+            warned = true;
+        }
+        let result = match item {
+            BlockItem::Decl(decl) => decl.check(scope).map(|_| true),
+            BlockItem::Stmt(stmt) => stmt.check(scope),
+        };
+        // a broken statement is assumed to fall through
+        falls_through &= scope.report(result).unwrap_or(true);
+    }
+    falls_through
+}
+
+/// whether control can fall through the end of the block
+impl Check for Block {
+    type Out = bool;
+
+    fn check<'ast>(&'ast self, scope: &mut Scope<'ast>) -> Result<Self::Out> {
+        scope.enter_scope();
+        let falls_through = check_items(&self.block_item, scope);
+        scope.exit_scope();
+        Ok(falls_through)
+    }
+}
+
+impl Check for Decl {
+    type Out = ();
+
+    fn check<'ast>(&'ast self, scope: &mut Scope<'ast>) -> Result<Self::Out> {
+        match self {
+            Decl::ConstDecl(const_decl) => {
+                for def in &const_decl.defs {
+                    let result = def.check(scope);
+                    scope.report(result);
+                }
+            }
+            Decl::VarDecl(var_decl) => {
+                for def in &var_decl.defs {
+                    let result = def.check(scope);
+                    scope.report(result);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Check for VarDef {
+    type Out = ();
+
+    fn check<'ast>(&'ast self, scope: &mut Scope<'ast>) -> Result<Self::Out> {
+        let dims = const_dims(&self.dims, scope);
+        let dims = scope.report(dims).unwrap_or_default();
+        if let Some(init_val) = &self.init_val {
+            // globals are initialized at compile time
+            let need_const = scope.in_global_scope();
+            let result = check_init(init_val, &dims, self.span, need_const, scope);
+            scope.report(result);
+        }
+        // added even if broken, so uses of it don't report again
+        let var = Var {
+            ty: array_or_int(dims),
+            values: None,
+            kind: var_kind(scope),
+            span: self.span,
+            used: false,
+        };
+        scope.add(&self.ident, var).map_err(|e| e.at(self.span))
+    }
+}
+
+impl Check for ConstDef {
+    type Out = ();
+
+    fn check<'ast>(&'ast self, scope: &mut Scope<'ast>) -> Result<Self::Out> {
+        let dims = const_dims(&self.dims, scope);
+        let dims = scope.report(dims).unwrap_or_default();
+        let values = check_init(&self.const_init_val, &dims, self.span, true, scope);
+        let var = Var {
+            ty: array_or_int(dims),
+            values: scope.report(values).flatten(),
+            kind: var_kind(scope),
+            span: self.span,
+            used: false,
+        };
+        scope.add(&self.ident, var).map_err(|e| e.at(self.span))
+    }
+}
+
+/// whether control can fall through to the next statement
+impl Check for Stmt {
+    type Out = bool;
+
+    fn check<'ast>(&'ast self, scope: &mut Scope<'ast>) -> Result<Self::Out> {
+        match self {
+            Stmt::Assign(lval, exp, _) => {
+                let var = scope.get(&lval.ident).map_err(|e| e.at(lval.span))?;
+                if var.values.is_some() {
+                    return Err(ErrorKind::ReassignConst(lval.ident.clone()).at(lval.span));
+                }
+                check_int(lval, lval.span, scope)?;
+                check_int(exp, exp.span(), scope)?;
+            }
+            Stmt::Exp(exp, _) => {
+                if let Some(exp) = exp {
+                    exp.check(scope)?;
+                }
+            }
+            Stmt::Block(block) => return block.check(scope),
+            Stmt::Return(exp, span) => {
+                let (func, ret) = scope.curr_func();
+                let result = match (ret, exp) {
+                    (Ty::Void, Some(_)) => Err(ErrorKind::ReturnValue(func.to_string()).at(*span)),
+                    (Ty::Void, None) => Ok(()),
+                    (_, None) => Err(ErrorKind::MissingReturnValue(func.to_string()).at(*span)),
+                    (_, Some(exp)) => check_int(exp, exp.span(), scope),
+                };
+                // ends the path even if broken
+                scope.report(result);
+                return Ok(false);
+            }
+            Stmt::If(if_stmt) => {
+                let cond = check_int(&if_stmt.cond, if_stmt.cond.span(), scope);
+                scope.report(cond);
+                let then = if_stmt.if_then.check(scope)?;
+                if let Some(else_then) = &if_stmt.else_then {
+                    return Ok(else_then.check(scope)? || then);
+                }
+            }
+            Stmt::While(while_stmt) => {
+                let cond = check_int(&while_stmt.cond, while_stmt.cond.span(), scope);
+                // a condition which isn't constant just isn't always true
+                let cond = scope.report(cond).and_then(|_| while_stmt.cond.eval(scope).ok());
+                scope.enter_loop();
+                let body = while_stmt.body.check(scope);
+                let has_break = scope.exit_loop();
+                body?;
+                if matches!(cond, Some(v) if v != 0) && !has_break {
+                    let msg = "loop condition is always true and the loop has no break";
+                    scope.warn(Warning::InfiniteLoop, while_stmt.span, msg.to_string());
+                    return Ok(false);
+                }
+            }
+            Stmt::Break(span) | Stmt::Continue(span) => {
+                if !scope.in_loop() {
+                    scope.report::<()>(Err(ErrorKind::NoInLoop.at(*span)));
+                } else if let Stmt::Break(_) = self {
+                    scope.add_break();
+                }
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl Check for ConstExp {
+    type Out = Ty;
+
+    fn check<'ast>(&'ast self, scope: &mut Scope<'ast>) -> Result<Self::Out> {
+        match self {
+            ConstExp::Exp(exp) => exp.check(scope),
+        }
+    }
+}
+
+impl Check for Exp {
+    type Out = Ty;
+
+    fn check<'ast>(&'ast self, scope: &mut Scope<'ast>) -> Result<Self::Out> {
+        match self {
+            Exp::LOrExp(exp) => exp.check(scope),
+        }
+    }
+}
+
+/// checks both operands are int, the values are only folded where a constant
+/// is required, so `1 / 0` is fine in code which never runs
+fn binary<'ast, A, B>(
+    (a, a_span): (&'ast A, Span),
+    (b, b_span): (&'ast B, Span),
+    scope: &mut Scope<'ast>,
+) -> Result<Ty>
+where
+    A: Check<Out = Ty>,
+    B: Check<Out = Ty>,
+{
+    check_int(a, a_span, scope)?;
+    check_int(b, b_span, scope)?;
+    Ok(Ty::Int)
+}
+
+impl Check for LOrExp {
+    type Out = Ty;
+
+    fn check<'ast>(&'ast self, scope: &mut Scope<'ast>) -> Result<Self::Out> {
+        match self {
+            LOrExp::LAndExp(exp) => exp.check(scope),
+            LOrExp::LOrExp(a, b, _) => {
+                binary((a.as_ref(), a.span()), (b, b.span()), scope)
+            },
+        }
+    }
+}
+
+impl Check for LAndExp {
+    type Out = Ty;
+
+    fn check<'ast>(&'ast self, scope: &mut Scope<'ast>) -> Result<Self::Out> {
+        match self {
+            LAndExp::EqExp(exp) => exp.check(scope),
+            LAndExp::LAndExp(a, b, _) => {
+                binary((a.as_ref(), a.span()), (b, b.span()), scope)
+            },
+        }
+    }
+}
+
+impl Check for EqExp {
+    type Out = Ty;
+
+    fn check<'ast>(&'ast self, scope: &mut Scope<'ast>) -> Result<Self::Out> {
+        match self {
+            EqExp::RelExp(exp) => exp.check(scope),
+            EqExp::EqExp(a, _, b, _) => {
+                binary((a.as_ref(), a.span()), (b, b.span()), scope)
+            },
+        }
+    }
+}
+
+impl Check for RelExp {
+    type Out = Ty;
+
+    fn check<'ast>(&'ast self, scope: &mut Scope<'ast>) -> Result<Self::Out> {
+        match self {
+            RelExp::AddExp(exp) => exp.check(scope),
+            RelExp::RelExp(a, _, b, _) => {
+                binary((a.as_ref(), a.span()), (b, b.span()), scope)
+            },
+        }
+    }
+}
+
+impl Check for AddExp {
+    type Out = Ty;
+
+    fn check<'ast>(&'ast self, scope: &mut Scope<'ast>) -> Result<Self::Out> {
+        match self {
+            AddExp::MulExp(exp) => exp.check(scope),
+            AddExp::AddAndMul(a, _, b, _) => {
+                binary((a.as_ref(), a.span()), (b, b.span()), scope)
+            },
+        }
+    }
+}
+
+impl Check for MulExp {
+    type Out = Ty;
+
+    fn check<'ast>(&'ast self, scope: &mut Scope<'ast>) -> Result<Self::Out> {
+        match self {
+            MulExp::UnaryExp(exp) => exp.check(scope),
+            MulExp::MulAndUnary(a, _, b, _) => {
+                binary((a.as_ref(), a.span()), (b, b.span()), scope)
+            },
+        }
+    }
+}
+
+impl Check for UnaryExp {
+    type Out = Ty;
+
+    fn check<'ast>(&'ast self, scope: &mut Scope<'ast>) -> Result<Self::Out> {
+        match self {
+            UnaryExp::PrimaryExp(exp) => exp.check(scope),
+            UnaryExp::UnaryOp(UnaryOp::Minus, exp, _) if is_int_min_literal(exp) => Ok(Ty::Int),
+            UnaryExp::UnaryOp(_, exp, _) => {
+                check_int(exp.as_ref(), exp.span(), scope)?;
+                Ok(Ty::Int)
+            }
+            UnaryExp::Call(call) => call.check(scope),
+        }
+    }
+}
+
+/// 2147483648 right under a minus, the only way to write INT_MIN
+fn is_int_min_literal(exp: &UnaryExp) -> bool {
+    matches!(exp, UnaryExp::PrimaryExp(PrimaryExp::Number(n, _)) if *n == 1 << 31)
+}
+
+impl Check for FuncCall {
+    type Out = Ty;
+
+    fn check<'ast>(&'ast self, scope: &mut Scope<'ast>) -> Result<Self::Out> {
+        if scope.is_local(&self.ident) {
+            return Err(ErrorKind::NotFunction(self.ident.clone()).at(self.span));
+        }
+        let sig = scope
+            .function(&self.ident)
+            .ok_or_else(|| ErrorKind::Undefined(self.ident.clone()).at(self.span))?;
+        let (ret, params) = (sig.ret.clone(), sig.params.clone());
+        if params.len() != self.args.len() {
+            return Err(ErrorKind::ArgCount {
+                func: self.ident.clone(),
+                expected: params.len(),
+                found: self.args.len(),
+            }
+            .at(self.span));
+        }
+        for (arg, param) in self.args.iter().zip(&params) {
+            let ty = arg.check(scope)?;
+            if ty == Ty::Void {
+                return Err(ErrorKind::VoidValue.at(arg.span()));
+            }
+            if !ty.fits(param) {
+                return Err(ErrorKind::Mismatch {
+                    expected: param.to_string(),
+                    found: ty,
+                }
+                .at(arg.span()));
+            }
+        }
+        Ok(ret)
+    }
+}
+
+impl Check for PrimaryExp {
+    type Out = Ty;
+
+    fn check<'ast>(&'ast self, scope: &mut Scope<'ast>) -> Result<Self::Out> {
+        match self {
+            PrimaryExp::Expression(exp) => exp.check(scope),
+            PrimaryExp::Number(n, span) => {
+                // the type is int all the same, the checks go on
+                if *n > i32::MAX as i64 {
+                    scope.errors.push(ErrorKind::LiteralOutOfRange.at(*span));
+                }
+                Ok(Ty::Int)
+            }
+            PrimaryExp::LVal(lval) => lval.check(scope),
+        }
+    }
+}
+
+impl Check for LVal {
+    type Out = Ty;
+
+    /// arrays, partly indexed or not, are values of array type
+    fn check<'ast>(&'ast self, scope: &mut Scope<'ast>) -> Result<Self::Out> {
+        let mut ty = scope
+            .get(&self.ident)
+            .map_err(|e| e.at(self.span))?
+            .ty
+            .clone();
+        for index in &self.indices {
+            ty = ty
+                .index()
+                .ok_or_else(|| ErrorKind::NotArray(self.ident.clone()).at(self.span))?;
+            check_int(index, index.span(), scope)?;
+        }
+        Ok(ty)
+    }
+}
@@ -0,0 +1,175 @@
+use crate::ast::*;
+
+use super::{ErrorKind, Result};
+
+/// `a / b` as RISC-V `div` computes it, `INT_MIN / -1` wraps to `INT_MIN`
+pub(crate) fn div(a: i32, b: i32) -> Option<i32> {
+    (b != 0).then(|| a.wrapping_div(b))
+}
+
+/// `a % b` as RISC-V `rem` computes it, the result takes the sign of `a`
+pub(crate) fn rem(a: i32, b: i32) -> Option<i32> {
+    (b != 0).then(|| a.wrapping_rem(b))
+}
+
+/// where the values of named constants come from
+pub trait Consts {
+    /// compile time value of `lval` at the already evaluated `indices`
+    fn value(&mut self, lval: &LVal, indices: &[i32]) -> Result<i32>;
+}
+
+/// compile time value of an expression, in i32 wrapping arithmetic, `&&` and
+/// `||` short-circuit as they do at run time
+pub trait Evaluate {
+    fn eval(&self, consts: &mut impl Consts) -> Result<i32>;
+}
+
+impl Evaluate for ConstExp {
+    fn eval(&self, consts: &mut impl Consts) -> Result<i32> {
+        match self {
+            ConstExp::Exp(exp) => exp.eval(consts),
+        }
+    }
+}
+
+impl Evaluate for Exp {
+    fn eval(&self, consts: &mut impl Consts) -> Result<i32> {
+        match self {
+            Exp::LOrExp(lor_exp) => lor_exp.eval(consts),
+        }
+    }
+}
+
+impl Evaluate for LOrExp {
+    fn eval(&self, consts: &mut impl Consts) -> Result<i32> {
+        match self {
+            LOrExp::LAndExp(land_exp) => land_exp.eval(consts),
+            LOrExp::LOrExp(lor_exp, land_exp, _) => {
+                if lor_exp.eval(consts)? != 0 {
+                    return Ok(1);
+                }
+                Ok((land_exp.eval(consts)? != 0) as i32)
+            }
+        }
+    }
+}
+
+impl Evaluate for LAndExp {
+    fn eval(&self, consts: &mut impl Consts) -> Result<i32> {
+        match self {
+            LAndExp::EqExp(eq_exp) => eq_exp.eval(consts),
+            LAndExp::LAndExp(land_exp, eq_exp, _) => {
+                if land_exp.eval(consts)? == 0 {
+                    return Ok(0);
+                }
+                Ok((eq_exp.eval(consts)? != 0) as i32)
+            }
+        }
+    }
+}
+
+impl Evaluate for EqExp {
+    fn eval(&self, consts: &mut impl Consts) -> Result<i32> {
+        match self {
+            EqExp::RelExp(rel_exp) => rel_exp.eval(consts),
+            EqExp::EqExp(eq_exp, eq_op, rel_exp, _) => {
+                let a = eq_exp.eval(consts)?;
+                let b = rel_exp.eval(consts)?;
+                match eq_op {
+                    EqOp::Eq => Ok((a == b) as i32),
+                    EqOp::NotEq => Ok((a != b) as i32),
+                }
+            }
+        }
+    }
+}
+
+impl Evaluate for RelExp {
+    fn eval(&self, consts: &mut impl Consts) -> Result<i32> {
+        match self {
+            RelExp::AddExp(add_exp) => add_exp.eval(consts),
+            RelExp::RelExp(rel_exp, rel_op, add_exp, _) => {
+                let a = rel_exp.eval(consts)?;
+                let b = add_exp.eval(consts)?;
+                match rel_op {
+                    RelOp::Gt => Ok((a > b) as i32),
+                    RelOp::Lt => Ok((a < b) as i32),
+                    RelOp::Ge => Ok((a >= b) as i32),
+                    RelOp::Le => Ok((a <= b) as i32),
+                }
+            }
+        }
+    }
+}
+
+impl Evaluate for AddExp {
+    fn eval(&self, consts: &mut impl Consts) -> Result<i32> {
+        match self {
+            AddExp::MulExp(mul_exp) => mul_exp.eval(consts),
+            AddExp::AddAndMul(add_exp, add_op, mul_exp, _) => {
+                let a = add_exp.eval(consts)?;
+                let b = mul_exp.eval(consts)?;
+                match add_op {
+                    AddOp::Add => Ok(a.wrapping_add(b)),
+                    AddOp::Sub => Ok(a.wrapping_sub(b)),
+                }
+            }
+        }
+    }
+}
+
+impl Evaluate for MulExp {
+    fn eval(&self, consts: &mut impl Consts) -> Result<i32> {
+        match self {
+            MulExp::UnaryExp(unary_exp) => unary_exp.eval(consts),
+            MulExp::MulAndUnary(mul_exp, mul_op, unary_exp, span) => {
+                let a = mul_exp.eval(consts)?;
+                let b = unary_exp.eval(consts)?;
+                let value = match mul_op {
+                    MulOp::Mul => Some(a.wrapping_mul(b)),
+                    MulOp::Div => div(a, b),
+                    MulOp::Mod => rem(a, b),
+                };
+                value.ok_or_else(|| ErrorKind::DivByZero.at(*span))
+            }
+        }
+    }
+}
+
+impl Evaluate for UnaryExp {
+    fn eval(&self, consts: &mut impl Consts) -> Result<i32> {
+        match self {
+            UnaryExp::PrimaryExp(primary_exp) => primary_exp.eval(consts),
+            UnaryExp::UnaryOp(unary_op, unary_exp, _) => {
+                let v = unary_exp.eval(consts)?;
+                match unary_op {
+                    UnaryOp::Add => Ok(v),
+                    UnaryOp::Minus => Ok(v.wrapping_neg()),
+                    UnaryOp::Not => Ok((v == 0) as i32),
+                }
+            }
+            UnaryExp::Call(call) => Err(ErrorKind::ConstCall(call.ident.clone()).at(call.span)),
+        }
+    }
+}
+
+impl Evaluate for PrimaryExp {
+    fn eval(&self, consts: &mut impl Consts) -> Result<i32> {
+        match self {
+            PrimaryExp::Expression(exp) => exp.eval(consts),
+            PrimaryExp::LVal(l_val) => l_val.eval(consts),
+            // 2147483648 wraps around to be negated back
+            PrimaryExp::Number(int, _) => Ok(*int as i32),
+        }
+    }
+}
+
+impl Evaluate for LVal {
+    fn eval(&self, consts: &mut impl Consts) -> Result<i32> {
+        let mut indices = vec![];
+        for index in &self.indices {
+            indices.push(index.eval(consts)?);
+        }
+        consts.value(self, &indices)
+    }
+}
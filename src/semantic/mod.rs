@@ -0,0 +1,185 @@
+use std::fmt;
+
+use crate::ast::{CompUnit, Span};
+use crate::diagnostic::Diagnostics;
+
+use check::Check;
+use scope::Scope;
+
+mod check;
+pub(crate) mod eval;
+mod scope;
+mod warning;
+
+pub use warning::{UnknownWarning, Warning, WarningOptions};
+
+/// type of a SysY expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Ty {
+    Int,
+    Void,
+    // int a[2][3] => Array([2, 3])
+    Array(Vec<usize>),
+    // int a[][3] => Pointer([3]), the pointer to its first element
+    Pointer(Vec<usize>),
+}
+
+impl Ty {
+    /// type of `self[i]`
+    fn index(&self) -> Option<Ty> {
+        let dims = match self {
+            Ty::Array(dims) => &dims[1..],
+            Ty::Pointer(dims) => &dims[..],
+            Ty::Int | Ty::Void => return None,
+        };
+        if dims.is_empty() {
+            Some(Ty::Int)
+        } else {
+            Some(Ty::Array(dims.to_vec()))
+        }
+    }
+
+    /// whether a value of `self` can be passed to a parameter of `param`
+    fn fits(&self, param: &Ty) -> bool {
+        match (self, param) {
+            (Ty::Int, Ty::Int) => true,
+            (Ty::Array(dims), Ty::Pointer(p_dims)) => dims[1..] == p_dims[..],
+            (Ty::Pointer(dims), Ty::Pointer(p_dims)) => dims == p_dims,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Ty::Int => write!(f, "int"),
+            Ty::Void => write!(f, "void"),
+            Ty::Array(dims) => {
+                write!(f, "int")?;
+                dims.iter().try_for_each(|dim| write!(f, "[{}]", dim))
+            }
+            Ty::Pointer(dims) => {
+                write!(f, "int[]")?;
+                dims.iter().try_for_each(|dim| write!(f, "[{}]", dim))
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ErrorKind {
+    Undefined(String),
+    Redeclare(String),
+    ReassignConst(String),
+    NotArray(String),
+    NoInLoop,
+    NotConst,
+    ConstCall(String),
+    DivByZero,
+    InvalidArraySize(i32),
+    LiteralOutOfRange,
+    InvalidInit(String),
+    VoidValue,
+    Mismatch {
+        expected: String,
+        found: Ty,
+    },
+    ArgCount {
+        func: String,
+        expected: usize,
+        found: usize,
+    },
+    ReturnValue(String),
+    MissingReturnValue(String),
+    ConflictingTypes(String),
+    NotFunction(String),
+    NoMain,
+}
+
+impl ErrorKind {
+    pub fn at(self, span: Span) -> Error {
+        Error { kind: self, span }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorKind::Undefined(name) => write!(f, "undefined: {}", name),
+            ErrorKind::Redeclare(name) => write!(f, "redeclaration of {}", name),
+            ErrorKind::ReassignConst(name) => write!(f, "assignment to const: {}", name),
+            ErrorKind::NotArray(name) => write!(f, "subscripted value is not an array: {}", name),
+            ErrorKind::NoInLoop => write!(f, "break/continue statement not within a loop"),
+            ErrorKind::NotConst => write!(f, "expression is not a compile time constant"),
+            ErrorKind::ConstCall(func) => {
+                write!(f, "call to {} is not allowed in a constant expression", func)
+            }
+            ErrorKind::DivByZero => write!(f, "division by zero in constant expression"),
+            ErrorKind::InvalidArraySize(size) => {
+                write!(f, "size of array must be positive, got {}", size)
+            }
+            ErrorKind::LiteralOutOfRange => write!(f, "integer literal is out of range"),
+            ErrorKind::InvalidInit(s) => write!(f, "invalid initializer: {}", s),
+            ErrorKind::VoidValue => write!(f, "void value not ignored as it ought to be"),
+            ErrorKind::Mismatch { expected, found } => {
+                write!(
+                    f,
+                    "mismatched types: expected {}, found {}",
+                    expected, found
+                )
+            }
+            ErrorKind::ArgCount {
+                func,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} expects {} argument(s), found {}",
+                func, expected, found
+            ),
+            ErrorKind::ReturnValue(func) => {
+                write!(f, "void function {} should not return a value", func)
+            }
+            ErrorKind::MissingReturnValue(func) => {
+                write!(f, "non-void function {} should return a value", func)
+            }
+            ErrorKind::ConflictingTypes(func) => write!(f, "conflicting types for {}", func),
+            ErrorKind::NotFunction(name) => write!(f, "called object is not a function: {}", name),
+            ErrorKind::NoMain => write!(f, "no `int main()` function is defined"),
+        }
+    }
+}
+
+/// an error pointing to the source code which causes it
+#[derive(Debug)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub span: Span,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// resolves names and checks types, so that `ir_gen` only sees valid programs
+pub fn check_program(comp_unit: &CompUnit, options: &WarningOptions, diags: &mut Diagnostics) {
+    let mut scope = Scope::new();
+    let result = comp_unit.check(&mut scope);
+    scope.report(result);
+
+    match scope.function("main") {
+        Some(main) if main.ret == Ty::Int && main.params.is_empty() && scope.is_defined("main") => {}
+        _ => scope.errors.push(ErrorKind::NoMain.at(Span::default())),
+    }
+    for err in scope.errors {
+        diags.error(err.span, err);
+    }
+    for (warning, span, msg) in scope.warnings {
+        options.report(warning, span, msg, diags);
+    }
+}
@@ -0,0 +1,228 @@
+use std::collections::{HashMap, HashSet};
+
+use super::eval::Consts;
+use super::warning::Warning;
+use super::{Error, ErrorKind, Ty};
+use crate::ast::{LVal, Span};
+
+/// scope errors don't know where they come from, the caller locates them
+type Result<T> = std::result::Result<T, ErrorKind>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VarKind {
+    Global,
+    Local,
+    Param,
+}
+
+pub struct Var {
+    pub ty: Ty,
+    // values of a const, flattened in row-major order
+    pub values: Option<Vec<i32>>,
+    pub kind: VarKind,
+    pub span: Span,
+    pub used: bool,
+}
+
+impl Var {
+    /// compile time value of `self[indices]`
+    pub fn value(&self, indices: &[i32]) -> Option<i32> {
+        let values = self.values.as_ref()?;
+        let dims = match &self.ty {
+            Ty::Int => &[][..],
+            Ty::Array(dims) => &dims[..],
+            Ty::Pointer(_) | Ty::Void => return None,
+        };
+        if indices.len() != dims.len() {
+            return None;
+        }
+        let mut pos = 0;
+        for (&index, &dim) in indices.iter().zip(dims) {
+            if index < 0 || index as usize >= dim {
+                return None;
+            }
+            pos = pos * dim + index as usize;
+        }
+        values.get(pos).copied()
+    }
+}
+
+impl Consts for Scope<'_> {
+    fn value(&mut self, lval: &LVal, indices: &[i32]) -> super::Result<i32> {
+        let var = self.get(&lval.ident).map_err(|e| e.at(lval.span))?;
+        var.value(indices)
+            .ok_or_else(|| ErrorKind::NotConst.at(lval.span))
+    }
+}
+
+#[derive(PartialEq, Eq)]
+pub struct FuncSig {
+    pub ret: Ty,
+    pub params: Vec<Ty>,
+}
+
+pub struct Scope<'ast> {
+    functions: HashMap<&'ast str, FuncSig>,
+    // functions with a body, builtins count as defined
+    defined: HashSet<&'ast str>,
+    // the first table holds the globals
+    symbol_tables: Vec<HashMap<&'ast str, Var>>,
+    // name and return type of the function being checked
+    curr_func: Option<(&'ast str, Ty)>,
+    // whether each enclosing loop has a `break`
+    loops: Vec<bool>,
+    pub errors: Vec<Error>,
+    pub warnings: Vec<(Warning, Span, String)>,
+}
+
+impl<'ast> Scope<'ast> {
+    pub fn new() -> Self {
+        let mut scope = Scope {
+            functions: HashMap::new(),
+            defined: HashSet::new(),
+            symbol_tables: vec![HashMap::new()],
+            curr_func: None,
+            loops: vec![],
+            errors: vec![],
+            warnings: vec![],
+        };
+        let ptr = || Ty::Pointer(vec![]);
+        for (name, ret, params) in [
+            ("getint", Ty::Int, vec![]),
+            ("getch", Ty::Int, vec![]),
+            ("getarray", Ty::Int, vec![ptr()]),
+            ("putint", Ty::Void, vec![Ty::Int]),
+            ("putch", Ty::Void, vec![Ty::Int]),
+            ("putarray", Ty::Void, vec![Ty::Int, ptr()]),
+            ("starttime", Ty::Void, vec![]),
+            ("stoptime", Ty::Void, vec![]),
+        ] {
+            scope.functions.insert(name, FuncSig { ret, params });
+            scope.defined.insert(name);
+        }
+        scope
+    }
+
+    /// keeps the error and goes on checking
+    pub fn report<T>(&mut self, result: super::Result<T>) -> Option<T> {
+        result.map_err(|e| self.errors.push(e)).ok()
+    }
+
+    pub fn warn(&mut self, warning: Warning, span: Span, msg: String) {
+        self.warnings.push((warning, span, msg));
+    }
+
+    pub fn in_global_scope(&self) -> bool {
+        self.symbol_tables.len() == 1
+    }
+
+    pub fn add(&mut self, k: &'ast str, v: Var) -> Result<()> {
+        let is_global = self.in_global_scope();
+        let table = self.symbol_tables.last_mut().unwrap();
+        if table.contains_key(k) || (is_global && self.functions.contains_key(k)) {
+            return Err(ErrorKind::Redeclare(k.to_string()));
+        }
+        let span = v.span;
+        table.insert(k, v);
+
+        let outer = self.symbol_tables.iter().rev().skip(1);
+        if !is_global && outer.into_iter().any(|table| table.contains_key(k)) {
+            let msg = format!("declaration of `{}` shadows a previous declaration", k);
+            self.warn(Warning::Shadow, span, msg);
+        }
+        Ok(())
+    }
+
+    /// looks up a variable and marks it as used
+    pub fn get(&mut self, k: &str) -> Result<&Var> {
+        let var = self
+            .symbol_tables
+            .iter_mut()
+            .rev()
+            .find_map(|table| table.get_mut(k))
+            .ok_or_else(|| ErrorKind::Undefined(k.to_string()))?;
+        var.used = true;
+        Ok(var)
+    }
+
+    /// whether a local of that name is in scope, it hides a function
+    pub fn is_local(&self, k: &str) -> bool {
+        self.symbol_tables[1..].iter().any(|table| table.contains_key(k))
+    }
+
+    /// declares a function, or defines it if `is_def`, every declaration
+    /// must have the same signature
+    pub fn add_function(&mut self, k: &'ast str, sig: FuncSig, is_def: bool) -> Result<()> {
+        if self.symbol_tables[0].contains_key(k) || (is_def && self.defined.contains(k)) {
+            return Err(ErrorKind::Redeclare(k.to_string()));
+        }
+        if matches!(self.functions.get(k), Some(prev) if *prev != sig) {
+            return Err(ErrorKind::ConflictingTypes(k.to_string()));
+        }
+        if is_def {
+            self.defined.insert(k);
+        }
+        self.functions.insert(k, sig);
+        Ok(())
+    }
+
+    pub fn is_defined(&self, k: &str) -> bool {
+        self.defined.contains(k)
+    }
+
+    pub fn function(&self, k: &str) -> Option<&FuncSig> {
+        self.functions.get(k)
+    }
+
+    pub fn enter_function(&mut self, name: &'ast str, ret: Ty) {
+        self.curr_func = Some((name, ret));
+    }
+
+    pub fn exit_function(&mut self) {
+        self.curr_func = None;
+    }
+
+    pub fn curr_func(&self) -> (&'ast str, &Ty) {
+        let (name, ret) = self.curr_func.as_ref().unwrap();
+        (name, ret)
+    }
+
+    /// { // enter scope
+    /// } // exit scope
+    pub fn enter_scope(&mut self) {
+        self.symbol_tables.push(HashMap::new());
+    }
+
+    /// warns about the variables never used in the scope
+    pub fn exit_scope(&mut self) {
+        let table = self.symbol_tables.pop().unwrap();
+        for (name, var) in table {
+            let (warning, what) = match var.kind {
+                _ if var.used => continue,
+                VarKind::Global => continue,
+                VarKind::Local => (Warning::UnusedVariable, "variable"),
+                VarKind::Param => (Warning::UnusedParameter, "parameter"),
+            };
+            self.warn(warning, var.span, format!("unused {} `{}`", what, name));
+        }
+    }
+
+    pub fn enter_loop(&mut self) {
+        self.loops.push(false);
+    }
+
+    /// returns whether the loop has a `break`
+    pub fn exit_loop(&mut self) -> bool {
+        self.loops.pop().unwrap()
+    }
+
+    pub fn in_loop(&self) -> bool {
+        !self.loops.is_empty()
+    }
+
+    pub fn add_break(&mut self) {
+        if let Some(has_break) = self.loops.last_mut() {
+            *has_break = true;
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::ast::Span;
+use crate::diagnostic::Diagnostics;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Warning {
+    UnusedVariable,
+    UnusedParameter,
+    UnreachableCode,
+    Shadow,
+    ReturnType,
+    InfiniteLoop,
+    ExcessInitializers,
+    Uninitialized,
+}
+
+impl Warning {
+    pub const ALL: [Warning; 8] = [
+        Warning::UnusedVariable,
+        Warning::UnusedParameter,
+        Warning::UnreachableCode,
+        Warning::Shadow,
+        Warning::ReturnType,
+        Warning::InfiniteLoop,
+        Warning::ExcessInitializers,
+        Warning::Uninitialized,
+    ];
+
+    /// name of the flag, -W<name> and -Wno-<name>
+    pub fn name(&self) -> &'static str {
+        match self {
+            Warning::UnusedVariable => "unused-variable",
+            Warning::UnusedParameter => "unused-parameter",
+            Warning::UnreachableCode => "unreachable-code",
+            Warning::Shadow => "shadow",
+            Warning::ReturnType => "return-type",
+            Warning::InfiniteLoop => "infinite-loop",
+            Warning::ExcessInitializers => "excess-initializers",
+            Warning::Uninitialized => "uninitialized",
+        }
+    }
+
+    /// the warnings -W<name> turns on, `all` and `extra` are groups like in gcc
+    fn from_flag(name: &str) -> Option<Vec<Warning>> {
+        match name {
+            "all" => Some(vec![
+                Warning::UnusedVariable,
+                Warning::UnreachableCode,
+                Warning::ReturnType,
+                Warning::InfiniteLoop,
+                Warning::Uninitialized,
+            ]),
+            "extra" => Some(vec![Warning::UnusedParameter]),
+            _ => Warning::ALL
+                .into_iter()
+                .find(|w| w.name() == name)
+                .map(|w| vec![w]),
+        }
+    }
+}
+
+/// a -W<name> or -Wno-<name> which names no warning
+#[derive(Debug)]
+pub struct UnknownWarning(String);
+
+impl fmt::Display for UnknownWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown warning option: {}", self.0)
+    }
+}
+
+/// which warnings are reported, by default only those gcc reports without
+/// any flag
+pub struct WarningOptions {
+    enabled: HashSet<Warning>,
+    // report warnings as errors
+    pub werror: bool,
+}
+
+impl Default for WarningOptions {
+    fn default() -> Self {
+        WarningOptions {
+            enabled: [Warning::ExcessInitializers].into(),
+            werror: false,
+        }
+    }
+}
+
+impl WarningOptions {
+    /// picks -Werror, -W<name> and -Wno-<name> out of the command line, later ones win
+    pub fn from_args(args: &[String]) -> Result<Self, UnknownWarning> {
+        let mut options = WarningOptions::default();
+        for arg in args {
+            match arg.strip_prefix("-W") {
+                Some("error") => options.werror = true,
+                Some("no-error") => options.werror = false,
+                Some(name) => {
+                    let (name, on) = match name.strip_prefix("no-") {
+                        Some(name) => (name, false),
+                        None => (name, true),
+                    };
+                    let warnings =
+                        Warning::from_flag(name).ok_or_else(|| UnknownWarning(arg.clone()))?;
+                    for w in warnings {
+                        if on {
+                            options.enabled.insert(w);
+                        } else {
+                            options.enabled.remove(&w);
+                        }
+                    }
+                }
+                None => {}
+            }
+        }
+        Ok(options)
+    }
+
+    pub fn is_enabled(&self, warning: Warning) -> bool {
+        self.enabled.contains(&warning)
+    }
+
+    /// adds `msg` to `diags` if `warning` is enabled, as an error with -Werror
+    pub fn report(&self, warning: Warning, span: Span, msg: String, diags: &mut Diagnostics) {
+        if !self.is_enabled(warning) {
+            return;
+        }
+        if self.werror {
+            diags.error(span, format!("{} [-Werror={}]", msg, warning.name()));
+        } else {
+            diags.warning(span, format!("{} [-W{}]", msg, warning.name()));
+        }
+    }
+}
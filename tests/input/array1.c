@@ -0,0 +1,19 @@
+int main() {
+  int a[10];
+  int m[3][4];
+  int i = 0;
+  while (i < 10) {
+    a[i] = i * i;
+    i = i + 1;
+  }
+  i = 0;
+  while (i < 3) {
+    int j = 0;
+    while (j < 4) {
+      m[i][j] = a[i + j] + j;
+      j = j + 1;
+    }
+    i = i + 1;
+  }
+  return m[2][3] + a[9];
+}
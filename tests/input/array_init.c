@@ -0,0 +1,13 @@
+int g[2][3] = {1, {2, 3}, 4};
+int z[4][2] = {{}, {1}, 2, 3};
+const int cg[3] = {10, 20, 30};
+
+int main() {
+  int a[2][3] = {1, 2, 3, {4}};
+  int b[3][2] = {{1, 2}, {3}};
+  int c[4] = {};
+  const int ca[2][2] = {{5, 6}, {7, 8}};
+  const int n = ca[1][0] + cg[2];
+  int i = 1;
+  return a[1][0] + b[1][0] + b[2][1] + c[3] + ca[i][i] + n + g[0][1] + g[0][2] + z[1][0] + z[2][1] + cg[i];
+}
@@ -0,0 +1,40 @@
+int sum(int n, int a[]) {
+  int i = 0;
+  int s = 0;
+  while (i < n) {
+    s = s + a[i];
+    i = i + 1;
+  }
+  return s;
+}
+
+int trace(int m[][3], int n) {
+  int i = 0;
+  int t = 0;
+  while (i < n) {
+    t = t + m[i][i];
+    i = i + 1;
+  }
+  m[0][0] = t;
+  return t;
+}
+
+int many(int a, int b, int c, int d, int e, int f, int g, int h, int i, int j[]) {
+  return a + b + c + d + e + f + g + h + i + j[1];
+}
+
+int main() {
+  int arr[10];
+  int n = getarray(arr);
+  int m[3][3] = {{1, 2, 3}, {4, 5, 6}, {7, 8, 9}};
+  putarray(n, arr);
+  putint(sum(n, arr));
+  putch(10);
+  putint(sum(3, m[1]));
+  putch(10);
+  putint(trace(m, 3));
+  putch(10);
+  putint(many(1, 2, 3, 4, 5, 6, 7, 8, 9, m[0]));
+  putch(10);
+  return m[0][0];
+}
@@ -0,0 +1,16 @@
+/*
+ * multi-line comment
+ * with ** stars ** and a / slash
+ */
+const int INT_MIN = -2147483648;
+int g = -2147483648; /* one */ int h = 0x7fffffff; /* two */
+
+int main() {
+  /**/ int a = -2147483648; /***/
+  int b = - 2147483648 /* spaced */;
+  // INT_MIN / -1 is not folded here, just INT_MIN + INT_MAX
+  if (a == INT_MIN && b == g) {
+    return (a + h) + 2; /* 1 */
+  }
+  return 0;
+}
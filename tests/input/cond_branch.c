@@ -0,0 +1,21 @@
+int count = 0;
+
+int tick(int v) {
+  count = count + 1;
+  return v;
+}
+
+int main() {
+  int a = 1, b = 0, r = 0;
+  if (a && !b) r = r + 1;
+  if (tick(0) && tick(1)) r = r + 100;
+  if (tick(1) || tick(0)) r = r + 2;
+  if (!(a || b)) r = r + 100;
+  else r = r + 4;
+  if (!!a && (b || a >= 1) && a <= 1) r = r + 8;
+  int i = 0;
+  while (i < 10 && !(i >= 5 && tick(1))) i = i + 1;
+  if (b <= -1 || a > 0 && i == 5) r = r + 16;
+  // 31 + 3 * 32
+  return r + count * 32;
+}
@@ -0,0 +1,16 @@
+int x = 0 && 1 / 0;
+int y = 1 || 1 / 0;
+const int n = 0;
+int a[2 || n / n];
+
+int main() {
+  int z = 1;
+  // never runs, so no constant is needed
+  if (0) {
+    z = 1 / 0;
+  }
+  while (n && z / n) {
+    z = z % 0;
+  }
+  return x + y + z + a[0];
+}
@@ -0,0 +1,18 @@
+const int min = -2147483647 - 1;
+const int quot = min / -1;
+const int rems[4] = {7 % 2, -7 % 2, 7 % -2, min % -1};
+
+int main() {
+  const int max = 2147483647;
+  const int over = max + 1;
+  int a[-(min / 2147483647)];
+  a[0] = over;
+  int x = -7;
+  int y = 2;
+  // folded and computed at runtime alike
+  putint(quot == over);
+  putint(rems[1] == x % y);
+  putint(rems[2] == -x % -y);
+  putint(rems[3]);
+  return a[0] == min;
+}
@@ -0,0 +1,8 @@
+int sum(int a[][3]) {
+  return a[0][0];
+}
+
+int main() {
+  int m[2][4];
+  return sum(m);
+}
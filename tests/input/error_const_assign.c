@@ -0,0 +1,5 @@
+int main() {
+  const int N = 3;
+  N = 4;
+  return N;
+}
@@ -0,0 +1,8 @@
+int f() { return 1; }
+int a[f()];
+int b[1 / 0];
+const int c = 1 && 2 % 0;
+int main() {
+  const int d = 0 || f();
+  return 0;
+}
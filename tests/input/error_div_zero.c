@@ -0,0 +1,6 @@
+const int n = 4;
+const int m = n % (n - 4);
+
+int main() {
+  return 0;
+}
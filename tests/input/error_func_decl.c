@@ -0,0 +1,14 @@
+int f(int a);
+void g();
+
+int f(int a, int b) {
+  return a + b;
+}
+
+int g() {
+  return h();
+}
+
+int main() {
+  return f(1, 2);
+}
@@ -0,0 +1,5 @@
+int main() {
+  int a = 2147483648;
+  int b = -6442450944;
+  return a + b + 99999999999999999999999 + -2147483648;
+}
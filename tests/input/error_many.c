@@ -0,0 +1,14 @@
+int f(int a) {
+  return a;
+}
+
+int main() {
+  int x = y;
+  x = f(1, 2);
+  while (z) {
+    x = x + w;
+  }
+  const int c = 1;
+  c = x;
+  return v;
+}
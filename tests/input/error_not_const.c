@@ -0,0 +1,6 @@
+int n = 3;
+int a[n];
+
+int main() {
+  return 0;
+}
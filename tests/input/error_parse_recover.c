@@ -0,0 +1,10 @@
+int g = ;
+
+int main() {
+  int a = 1 +;
+  a = a 2;
+  if (a > 1 {
+    a = 3;
+  }
+  return a;
+}
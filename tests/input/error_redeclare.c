@@ -0,0 +1,6 @@
+int a;
+int a[2] = {1, 2};
+
+int main() {
+  return 0;
+}
@@ -0,0 +1,10 @@
+int f(int x) {
+  if (x) {
+    return;
+  }
+  return x;
+}
+
+int main() {
+  return f(1);
+}
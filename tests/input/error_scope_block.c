@@ -0,0 +1,11 @@
+int x;
+int x;
+
+int main() {
+  int a = 1;
+  {
+    int b = a;
+    int b = 2;
+  }
+  return b;
+}
@@ -0,0 +1,8 @@
+int f() {
+  return 1;
+}
+
+int main() {
+  int f = 2;
+  return f();
+}
@@ -0,0 +1,8 @@
+int f(int a) {
+  int a = 1;
+  return a;
+}
+
+int main() {
+  return f(0);
+}
@@ -0,0 +1,8 @@
+void f(void x) {
+}
+
+int main() {
+  void y;
+  const void z = 1;
+  return 0;
+}
@@ -0,0 +1,6 @@
+void f() {}
+
+int main() {
+  int a = f() + 1;
+  return a;
+}
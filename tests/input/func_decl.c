@@ -0,0 +1,31 @@
+const int N = 3;
+
+int getint();
+int is_odd(int n);
+int sum(int n, int a[][N]);
+
+int is_even(int n) {
+  if (n == 0) return 1;
+  return is_odd(n - 1);
+}
+
+int is_odd(int x) {
+  if (x == 0) return 0;
+  return is_even(x - 1);
+}
+
+int main() {
+  int a[2][N] = {{1, 2, 3}, {4, 5, 6}};
+  putint(is_even(10));
+  putint(is_odd(7));
+  return sum(2, a);
+}
+
+int sum(int n, int b[][N]) {
+  int i = 0, s = 0;
+  while (i < n * N) {
+    s = s + b[i / N][i % N];
+    i = i + 1;
+  }
+  return s;
+}
@@ -0,0 +1,10 @@
+int g[5];
+int board[4][4];
+
+int main() {
+  g[0] = 3;
+  g[4] = g[0] + 1;
+  board[1][2] = g[4] * 2;
+  board[3][3] = board[1][2] + board[0][0];
+  return board[3][3] + g[1];
+}
@@ -0,0 +1,25 @@
+// every function has the same blocks, their labels must not clash
+int abs(int x) {
+  if (x < 0) {
+    return -x;
+  } else {
+    return x;
+  }
+}
+
+int sign(int x) {
+  if (x < 0) {
+    return -1;
+  } else {
+    return x != 0;
+  }
+}
+
+int main() {
+  int i = -2, sum = 0;
+  while (i < 3) {
+    sum = sum + abs(i) * 10 + sign(i);
+    i = i + 1;
+  }
+  return sum;
+}
@@ -0,0 +1,22 @@
+int id(int x) { return x; }
+int many(int a, int b, int c, int d, int e, int f, int g, int h, int i, int j, int k) {
+  return a - b + c - d + e - f + g - h + i - j + k;
+}
+int main() {
+  int a0 = id(1), a1 = id(2), a2 = id(3), a3 = id(4), a4 = id(5), a5 = id(6);
+  int a6 = id(7), a7 = id(8), a8 = id(9), a9 = id(10), b0 = id(11), b1 = id(12);
+  int b2 = id(13), b3 = id(14), b4 = id(15), b5 = id(16), b6 = id(17), b7 = id(18);
+  int i = 0, s = 0;
+  while (i < 5) {
+    int t = a0 * b7 + a1 * b6 + a2 * b5 + a3 * b4 + a4 * b3 + a5 * b2 + a6 * b1 + a7 * b0 + a8 * a9;
+    s = s + t + many(a0, a1, a2, a3, a4, a5, a6, a7, a8, a9, i);
+    int u0 = s + 1, u1 = s + 2, u2 = s + 3, u3 = s + 4, u4 = s + 5, u5 = s + 6, u6 = s + 7;
+    int u7 = s + 8, u8 = s + 9, u9 = s + 10, v0 = s + 11, v1 = s + 12, v2 = s + 13, v3 = s + 14;
+    int v4 = s + 15, v5 = s + 16, v6 = s + 17, v7 = s + 18, v8 = s + 19, v9 = s + 20, w = s + 21;
+    s = (u0 * u1 + u2 * u3 + u4 * u5 + u6 * u7 + u8 * u9 + v0 * v1 + v2 * v3 + v4 * v5 + v6 * v7 + v8 * v9 + w + u0 + u9 + v9 + v0) % 10007;
+    i = i + 1;
+  }
+  putint(s);
+  putch(10);
+  return s % 256;
+}
@@ -0,0 +1,10 @@
+// a local may take the name of a function, hiding it
+int f() {
+  return 3;
+}
+
+int main() {
+  int r = f();
+  int f = 4;
+  return r + f;
+}
@@ -0,0 +1,19 @@
+// a local hides a global of the same name until the end of its block
+int x = 10;
+const int n = 3;
+
+int get_x() {
+  return x;
+}
+
+int main() {
+  putint(x);
+  int x = 1;
+  {
+    int x = 2;
+    const int n = 5;
+    putint(x + n);
+  }
+  putint(x + n);
+  return x + get_x();
+}
@@ -0,0 +1,12 @@
+// parameters live in the outermost scope of the body, inner blocks may hide them
+int f(int a, int b) {
+  {
+    int a = b * 2;
+    b = a;
+  }
+  return a + b;
+}
+
+int main() {
+  return f(1, 3);
+}
@@ -0,0 +1,39 @@
+int gcd(int a, int b) {
+  while (b) {
+    int t = a % b;
+    a = b;
+    b = t;
+  }
+  return a;
+}
+
+// more params than registers, a and b swap through the loop
+int swap(int a, int b, int c, int d, int e, int f, int g, int h, int i, int j) {
+  int k = 0;
+  while (k < 3) {
+    int t = a;
+    a = b;
+    b = t;
+    k = k + 1;
+  }
+  return a * 1000 + b * 100 + j * 10 + i;
+}
+
+int main() {
+  int x = 0, i = 0, s = 1, u;
+  while (i < 10) {
+    i = i + 1;
+    if (i == 3) continue;
+    if (i > 7) break;
+    x = x + i;
+    if (x > 5 && s) s = s + 1;
+    else s = s * 2;
+  }
+  // x = 25, i = 8, s = 8
+  if (x > 100) u = 1;
+  else u = x > 3 || i;
+  putint(gcd(x, 15));
+  putch(10);
+  // 2149 % 256 = 101
+  return swap(1, 2, 3, 4, 5, 6, 7, 8, 9, 4) % 256 + x + s + u;
+}
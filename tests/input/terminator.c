@@ -0,0 +1,31 @@
+// statements after return, break and continue are never generated
+int f(int x) {
+  if (x) {
+    return 1;
+    x = 2;
+  } else {
+    return 2;
+  }
+  return 3;
+}
+
+int main() {
+  int i = 0, s = 0;
+  while (i < 10) {
+    i = i + 1;
+    if (i == 2) {
+      continue;
+      s = s + 100;
+    }
+    if (i == 6) {
+      break;
+      s = s + 1000;
+    }
+    s = s + i;
+  }
+  while (1) {
+    break;
+  }
+  return s + f(0) + f(1);
+  s = 5;
+}
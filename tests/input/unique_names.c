@@ -0,0 +1,35 @@
+int x = 1;
+
+int then(int then, int entry) {
+  int x = then;
+  {
+    int x = entry;
+    then = then + x;
+  }
+  {
+    int x = 2;
+    then = then * x;
+  }
+  return then + x;
+}
+
+int main() {
+  int x_1 = 0;
+  int i = 0;
+  while (i < 4) {
+    if (i == 1 || i == 3) {
+      x_1 = x_1 + 1;
+    } else {
+      x_1 = x_1 + 2;
+    }
+    i = i + 1;
+  }
+  while (i > 0) {
+    if (i && x_1) {
+      int x = i;
+      x_1 = x_1 + x;
+    }
+    i = i - 1;
+  }
+  return then(x_1, x) + x;
+}
@@ -0,0 +1,4 @@
+int main() {
+  int a[2][3] = {1, {2, 3}, 4};
+  return a[0][1] + a[0][2];
+}
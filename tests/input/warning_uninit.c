@@ -0,0 +1,20 @@
+int f(int n) {
+  int x;
+  int y;
+  int z = 1;
+  if (n) {
+    y = 2;
+  }
+  while (n) {
+    z = z + x;
+    n = n - 1;
+  }
+  return x + y + z;
+}
+
+int main() {
+  int a[2];
+  int b;
+  b = 1;
+  return f(a[0]) + b;
+}
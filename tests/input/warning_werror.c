@@ -0,0 +1,15 @@
+int f(int x, int y) {
+  int unused = 1;
+  if (x) {
+    int x = 2;
+    return x;
+  }
+}
+
+int main() {
+  int a = f(1, 2);
+  while (1) {
+    a = a + 1;
+  }
+  return a;
+}